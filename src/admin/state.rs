@@ -31,7 +31,7 @@ impl EditState {
             InputField::new("log_level", config.log_level.as_deref().unwrap_or_default(), Some("Info / Debug / Error")),
             InputField::new("log_file", config.log_file.as_deref().unwrap_or_default(), Some("Log file path(Optional)")),
             InputField::new("log_stdout", config.log_stdout.map(|b| b.to_string()).as_deref().unwrap_or(""), Some("true / false")),
-            InputField::new("max_file_size_mb", config.max_file_size_mb.map(|n| n.to_string()).as_deref().unwrap_or(""), Some("Max file size(MB)")),
+            InputField::new("max_file_size_mb", config.max_file_size_mb.map(|n| n.to_string()).as_deref().unwrap_or(""), Some("Max file size(MB), 0 = unlimited")),
         ];
 
         EditState {
@@ -89,31 +89,39 @@ impl EditState {
             InputField::new("source.path", &profile.source.path, Some("送信元パス")),
             InputField::new("source.host", profile.source.host.as_deref().unwrap_or_default(), Some("Hostname")),
             InputField::new("source.port", &profile.source.port.map(|p| p.to_string()).unwrap_or_default(), Some("Port No")),
-            InputField::new("source.trigger", &profile.source.trigger.kind.to_string(), Some("Manual / Schedule")),
+            InputField::new("source.trigger", &profile.source.trigger.kind.to_string(), Some("Manual / Schedule / Watch")),
             InputField::new("source.schedule", profile.source.trigger.schedule.as_deref().unwrap_or_default(), Some("cron format")),
 
-            InputField::new("source.auth.method", &profile.source.authentication.as_ref().map(|a| a.method.to_string()).unwrap_or_default(), Some("Password / PrivateKey / EnvKey / SshConfig")),
+            InputField::new("source.auth.method", &profile.source.authentication.as_ref().map(|a| a.method.to_string()).unwrap_or_default(), Some("Password / PrivateKey / EnvKey / SshConfig / Agent / KeyboardInteractive")),
             InputField::new("source.auth.username", &profile.source.authentication.as_ref().map(|a| a.username.clone()).unwrap_or_default(), None),
             InputField::new("source.auth.password_ref", &profile.source.authentication.as_ref().and_then(|a| a.password_ref.clone()).unwrap_or_default(), None),
             InputField::new("source.auth.private_key_ref", &profile.source.authentication.as_ref().and_then(|a| a.private_key_ref.clone()).unwrap_or_default(), None),
+            InputField::new("source.auth.passphrase_ref", &profile.source.authentication.as_ref().and_then(|a| a.passphrase_ref.clone()).unwrap_or_default(), Some("Env var or file:/path")),
             InputField::new("source.auth.ssh_config_alias", &profile.source.authentication.as_ref().and_then(|a| a.ssh_config_alias.clone()).unwrap_or_default(), None),
+            InputField::new("source.auth.ssh_config_file", &profile.source.authentication.as_ref().and_then(|a| a.ssh_config_file.clone()).unwrap_or_default(), Some("Defaults to ~/.ssh/config")),
 
             // Destination
             InputField::new("destination.type", &profile.destination.kind.to_string(), Some("Local / Sftp / Scp")),
             InputField::new("destination.path", &profile.destination.path, Some("Destination file path")),
             InputField::new("destination.host", profile.destination.host.as_deref().unwrap_or_default(), Some("Hostname")),
             InputField::new("destination.port", &profile.destination.port.map(|p| p.to_string()).unwrap_or_default(), Some("Port No")),
-            InputField::new("destination.auth.method", &profile.destination.authentication.as_ref().map(|a| a.method.to_string()).unwrap_or_default(), Some("Password / PrivateKey / EnvKey / SshConfig")),
+            InputField::new("destination.auth.method", &profile.destination.authentication.as_ref().map(|a| a.method.to_string()).unwrap_or_default(), Some("Password / PrivateKey / EnvKey / SshConfig / Agent / KeyboardInteractive")),
             InputField::new("destination.auth.username", &profile.destination.authentication.as_ref().map(|a| a.username.clone()).unwrap_or_default(), None),
             InputField::new("destination.auth.password_ref", &profile.destination.authentication.as_ref().and_then(|a| a.password_ref.clone()).unwrap_or_default(), None),
             InputField::new("destination.auth.private_key_ref", &profile.destination.authentication.as_ref().and_then(|a| a.private_key_ref.clone()).unwrap_or_default(), None),
+            InputField::new("destination.auth.passphrase_ref", &profile.destination.authentication.as_ref().and_then(|a| a.passphrase_ref.clone()).unwrap_or_default(), Some("Env var or file:/path")),
             InputField::new("destination.auth.ssh_config_alias", &profile.destination.authentication.as_ref().and_then(|a| a.ssh_config_alias.clone()).unwrap_or_default(), None),
+            InputField::new("destination.auth.ssh_config_file", &profile.destination.authentication.as_ref().and_then(|a| a.ssh_config_file.clone()).unwrap_or_default(), Some("Defaults to ~/.ssh/config")),
 
             // Transfer Settings
             InputField::new("transfer_protocol", &profile.transfer_protocol.protocol.to_string(), Some("SFTP / SCP")),
             InputField::new("pre_transfer_command", profile.pre_transfer_command.as_deref().unwrap_or_default(), Some("Pre transfer command(Optional)")),
             InputField::new("post_transfer_command", profile.post_transfer_command.as_deref().unwrap_or_default(), Some("Post trasnfer command(Optional)")),
             InputField::new("on_error_command", profile.on_error_command.as_deref().unwrap_or_default(), Some("On error command(Optional)")),
+            InputField::new("recursive", profile.recursive.map(|b| b.to_string()).as_deref().unwrap_or(""), Some("true / false")),
+            InputField::new("resume", profile.resume.map(|b| b.to_string()).as_deref().unwrap_or(""), Some("true / false")),
+            InputField::new("verify", &profile.verify.as_ref().map(|v| v.to_string()).unwrap_or_default(), Some("sha256 / md5 / size (Optional)")),
+            InputField::new("max_file_size_mb", profile.max_file_size_mb.map(|n| n.to_string()).as_deref().unwrap_or(""), Some("Overrides config.yaml (MB, 0 = unlimited)")),
         ];
 
         EditState {
@@ -156,6 +164,11 @@ impl EditState {
                             password_ref: None,
                             private_key_ref: None,
                             ssh_config_alias: None,
+                            ssh_config_file: None,
+                            passphrase_ref: None,
+                            prompts: None,
+                            host_key_fingerprint: None,
+                            jump_hosts: None,
                         });
                     } else if let Some(auth) = &mut profile.source.authentication {
                         auth.method = field.value.parse().unwrap_or(AuthenticationMethod::Password);
@@ -176,11 +189,21 @@ impl EditState {
                         auth.private_key_ref = if field.value.is_empty() { None } else { Some(field.value.clone()) };
                     }
                 }
+                "source.auth.passphrase_ref" => {
+                    if let Some(auth) = &mut profile.source.authentication {
+                        auth.passphrase_ref = if field.value.is_empty() { None } else { Some(field.value.clone()) };
+                    }
+                }
                 "source.auth.ssh_config_alias" => {
                     if let Some(auth) = &mut profile.source.authentication {
                         auth.ssh_config_alias = if field.value.is_empty() { None } else { Some(field.value.clone()) };
                     }
                 }
+                "source.auth.ssh_config_file" => {
+                    if let Some(auth) = &mut profile.source.authentication {
+                        auth.ssh_config_file = if field.value.is_empty() { None } else { Some(field.value.clone()) };
+                    }
+                }
 
                 // Destination
                 "destination.type" => profile.destination.kind = field.value.parse().unwrap_or(DestinationType::Local),
@@ -195,6 +218,11 @@ impl EditState {
                             password_ref: None,
                             private_key_ref: None,
                             ssh_config_alias: None,
+                            ssh_config_file: None,
+                            passphrase_ref: None,
+                            prompts: None,
+                            host_key_fingerprint: None,
+                            jump_hosts: None,
                         });
                     } else if let Some(auth) = &mut profile.destination.authentication {
                         auth.method = field.value.parse().unwrap_or(AuthenticationMethod::Password);
@@ -215,11 +243,21 @@ impl EditState {
                         auth.private_key_ref = if field.value.is_empty() { None } else { Some(field.value.clone()) };
                     }
                 }
+                "destination.auth.passphrase_ref" => {
+                    if let Some(auth) = &mut profile.destination.authentication {
+                        auth.passphrase_ref = if field.value.is_empty() { None } else { Some(field.value.clone()) };
+                    }
+                }
                 "destination.auth.ssh_config_alias" => {
                     if let Some(auth) = &mut profile.destination.authentication {
                         auth.ssh_config_alias = if field.value.is_empty() { None } else { Some(field.value.clone()) };
                     }
                 }
+                "destination.auth.ssh_config_file" => {
+                    if let Some(auth) = &mut profile.destination.authentication {
+                        auth.ssh_config_file = if field.value.is_empty() { None } else { Some(field.value.clone()) };
+                    }
+                }
 
                 // Transfer
                 "transfer_protocol" => {
@@ -234,6 +272,22 @@ impl EditState {
                 "on_error_command" => {
                     profile.on_error_command = if field.value.is_empty() { None } else { Some(field.value.clone()) };
                 }
+                "recursive" => {
+                    profile.recursive = match field.value.trim().to_lowercase().as_str() {
+                        "true" => Some(true),
+                        "false" => Some(false),
+                        _ => None,
+                    };
+                }
+                "resume" => {
+                    profile.resume = match field.value.trim().to_lowercase().as_str() {
+                        "true" => Some(true),
+                        "false" => Some(false),
+                        _ => None,
+                    };
+                }
+                "verify" => profile.verify = field.value.trim().parse().ok(),
+                "max_file_size_mb" => profile.max_file_size_mb = field.value.trim().parse::<u64>().ok(),
 
                 _ => {}
             }
@@ -4,7 +4,7 @@ use ratatui::{
     widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap},
     Frame,
 };
-use crate::{AdminMode, AdminState, EditState, SourceType, UiState};
+use crate::{AdminMode, AdminState, EditState, SourceType, UiState, DEFAULT_MAX_FILE_SIZE_MB};
 
 pub fn render_admin(f: &mut Frame, state: &AdminState) {
     // レイアウトをモードに応じて決定
@@ -158,7 +158,7 @@ fn render_config_summary(f: &mut Frame, area: Rect, state: &AdminState) {
             Cell::from("Max File Size(MB)"),
             Cell::from(cfg
                 .max_file_size_mb
-                .unwrap_or(500)
+                .unwrap_or(DEFAULT_MAX_FILE_SIZE_MB)
                 .to_string()),
         ]),
     ];
@@ -1,7 +1,12 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
-use crate::{process_transfer_profile, run_admin_ui, AppConfig, AppError, Profile};
+use crate::{
+    load_configuration, process_transfer_profile_with_context, run_admin_ui, run_daemon, AppConfig, AppError, LogProgress,
+    Profile, ProgressObserver, TerminalProgress, TransferContext, TransferOptions, TransferReport,
+};
+use log::warn;
+use std::{io::IsTerminal, sync::Arc, time::Duration};
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -19,15 +24,50 @@ pub enum Commands {
     Transfer {
         #[arg(short, long)]
         profile_id: String,
+        // 転送結果の出力形式（jsonの場合はTransferReportを標準出力に書き出す）
+        // Output format of the result (json writes the TransferReport to stdout)
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+        // 同じプロファイルを別のプロセスが実行中の場合に待つ秒数（config.yamlのlockWaitSecsより優先）
+        // Seconds to wait while another process runs the same profile (overrides lockWaitSecs in config.yaml)
+        #[arg(long, value_name = "SECS")]
+        lock_wait: Option<u64>,
     },
+    #[command(name = "daemon")]
+    #[command(about = "Run profiles with a schedule or watch trigger until stopped (SIGHUP reloads, SIGTERM stops)")]
+    Daemon,
     #[command(name = "admin")]
     #[command(about = "Manages configuration settings and transfer profile information")]
     Admin,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl Cli {
+    /// 標準出力を機械可読な出力に使うかどうか（その場合、ログは標準エラー出力へ送る）
+    /// Whether stdout carries machine-readable output (logs then go to stderr)
+    pub fn json_output(&self) -> bool {
+        matches!(
+            self.command,
+            Commands::Transfer {
+                output: OutputFormat::Json,
+                ..
+            }
+        )
+    }
+}
+
 pub async fn dispatch(cli: Cli, profiles: Profile, app_config: AppConfig) -> Result<()> {
     match cli.command {
-        Commands::Transfer { profile_id } => {
+        Commands::Transfer {
+            profile_id,
+            output,
+            lock_wait,
+        } => {
             // profile_id に該当する TransferProfile を探す
             // Find the TransferProfile that matches the profile_id
             match profiles
@@ -35,7 +75,35 @@ pub async fn dispatch(cli: Cli, profiles: Profile, app_config: AppConfig) -> Res
                 .into_iter()
                 .find(|p| p.profile_id == profile_id)
             {
-                Some(profile) => process_transfer_profile(profile).await,
+                Some(profile) => {
+                    // Ctrl+Cで実行中の転送をキャンセルする
+                    // Ctrl+C cancels the running transfer
+                    let mut options = TransferOptions::from_app_config(&app_config);
+                    if let Some(secs) = lock_wait {
+                        options.lock_wait = (secs > 0).then(|| Duration::from_secs(secs));
+                    }
+                    let context = TransferContext::default()
+                        .with_progress(progress_observer(output))
+                        .with_options(options);
+                    let cancel = context.cancel.clone();
+                    tokio::spawn(async move {
+                        if tokio::signal::ctrl_c().await.is_ok() {
+                            warn!("Interrupted. Cancelling the transfer...");
+                            cancel.cancel();
+                        }
+                    });
+                    let result = process_transfer_profile_with_context(profile, context).await;
+                    if output == OutputFormat::Json {
+                        let report = match &result {
+                            Ok(report) => Some(report),
+                            Err(e) => e.downcast_ref::<TransferReport>(),
+                        };
+                        if let Some(report) = report {
+                            println!("{}", report.to_json()?);
+                        }
+                    }
+                    result.map(|_| ())
+                }
                 None => {
                     return Err(AppError::Validation(format!(
                         "Profile '{}' not found in config.yaml",
@@ -45,8 +113,25 @@ pub async fn dispatch(cli: Cli, profiles: Profile, app_config: AppConfig) -> Res
                 }
             }
         }
+        Commands::Daemon => {
+            let config_path = cli.config;
+            run_daemon(app_config, profiles, move || {
+                load_configuration(config_path.as_deref())
+            })
+            .await
+        }
         Commands::Admin => {
             run_admin_ui(app_config, profiles)
         }
     }
 }
+
+// 標準出力が端末なら進捗バーを、それ以外（パイプやcron、JSON出力）では一定間隔のログを使う
+// A progress bar when stdout is a terminal, periodic log lines otherwise (pipes, cron, JSON output)
+fn progress_observer(output: OutputFormat) -> Arc<dyn ProgressObserver> {
+    if output == OutputFormat::Text && std::io::stdout().is_terminal() {
+        Arc::new(TerminalProgress::new())
+    } else {
+        Arc::new(LogProgress::default())
+    }
+}
@@ -6,6 +6,8 @@ use etcetera::{choose_base_strategy, BaseStrategy};
 use log::error;
 use serde::{Deserialize, Serialize};
 
+use crate::BandwidthLimit;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AppConfig {
@@ -14,6 +16,50 @@ pub struct AppConfig {
     pub log_file: Option<String>,
     pub log_stdout: Option<bool>,
     pub max_file_size_mb: Option<u64>,
+    // SSH接続のタイムアウト設定（プロファイル側の設定で上書き可能）
+    // SSH connection timeouts (can be overridden per profile)
+    pub timeouts: Option<TimeoutSettings>,
+    // ホスト鍵の検証設定（プロファイル側の設定で上書き可能）
+    // Host key verification settings (can be overridden per profile)
+    pub host_keys: Option<HostKeySettings>,
+    // すべてのプロファイルに適用する転送速度の上限（プロファイル側の上限と小さい方を使う）
+    // Transfer rate cap applied to every profile (the lower of this and the profile's limit wins)
+    pub bandwidth_limit: Option<BandwidthLimit>,
+    // デーモンが各プロファイルの最終実行時刻を保存するファイル（未指定の場合はデータディレクトリ）
+    // File where the daemon keeps each profile's last run time (the data directory when omitted)
+    pub schedule_state_file: Option<String>,
+    // 同じプロファイルの同時実行を防ぐロックファイルの置き場所（未指定の場合はランタイムディレクトリ）
+    // Where the locks preventing concurrent runs of a profile are kept (the runtime directory when omitted)
+    pub lock_dir: Option<String>,
+    // 別のプロセスが同じプロファイルを実行中の場合に待つ秒数（未指定か0の場合は待たずに失敗する）
+    // Seconds to wait while another process runs the same profile (fails right away when omitted or 0)
+    pub lock_wait_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeoutSettings {
+    // TCP接続・SSHハンドシェイク・各操作のタイムアウト（秒、0で無効）
+    // Timeouts for the TCP connect, the SSH handshake and each blocking operation (seconds, 0 disables)
+    pub connect_secs: Option<u64>,
+    pub handshake_secs: Option<u64>,
+    pub operation_secs: Option<u64>,
+    // SSHキープアライブの送信間隔（秒、0で無効）
+    // Interval between SSH keepalive messages (seconds, 0 disables)
+    pub keepalive_secs: Option<u32>,
+}
+
+impl TimeoutSettings {
+    /// 未設定の項目を `fallback` の値で補う
+    /// Fill the unset fields from `fallback`
+    pub fn or(&self, fallback: &TimeoutSettings) -> TimeoutSettings {
+        TimeoutSettings {
+            connect_secs: self.connect_secs.or(fallback.connect_secs),
+            handshake_secs: self.handshake_secs.or(fallback.handshake_secs),
+            operation_secs: self.operation_secs.or(fallback.operation_secs),
+            keepalive_secs: self.keepalive_secs.or(fallback.keepalive_secs),
+        }
+    }
 }
 
 impl AppConfig {
@@ -35,9 +81,59 @@ impl AppConfig {
             .context("Failed to deserialize AppConfig")
     }
 
+    /// `path` が指定されていればそのファイルを、なければ既定の場所のconfig.yamlを読み込む
+    /// Load the file at `path` when given, otherwise config.yaml from the default location
+    pub fn load(path: Option<&str>) -> Result<AppConfig> {
+        match path {
+            Some(path) => AppConfig::over_ride_config(path),
+            None => AppConfig::load_config()
+                .context("Failed to load default application configuration"),
+        }
+    }
+
     pub fn over_ride_config(path: &str) -> Result<AppConfig> {
         let yaml = fs::read_to_string(path)?;
         let app_config: AppConfig = serde_yaml::from_str(&yaml)?;
         Ok(app_config)
     }
 }
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostKeySettings {
+    // 検証ポリシー（strict / accept-new / off、未指定の場合は strict）
+    // Verification policy (strict / accept-new / off, strict when omitted)
+    pub policy: Option<HostKeyPolicy>,
+    // known_hostsファイルのパス（未指定の場合は ~/.ssh/known_hosts）
+    // Path of the known_hosts file (~/.ssh/known_hosts when omitted)
+    pub known_hosts_file: Option<String>,
+}
+
+impl HostKeySettings {
+    /// 未設定の項目を `fallback` の値で補う
+    /// Fill the unset fields from `fallback`
+    pub fn or(&self, fallback: &HostKeySettings) -> HostKeySettings {
+        HostKeySettings {
+            policy: self.policy.clone().or(fallback.policy.clone()),
+            known_hosts_file: self
+                .known_hosts_file
+                .clone()
+                .or(fallback.known_hosts_file.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HostKeyPolicy {
+    // known_hostsに登録済みの鍵のみ受け入れる
+    // Only accept keys already listed in known_hosts
+    #[default]
+    Strict,
+    // 未登録のホストは鍵を追記して受け入れ、鍵が変わった場合は拒否する
+    // Record and accept keys of unknown hosts, but reject changed keys
+    AcceptNew,
+    // 検証しない
+    // Do not verify
+    Off,
+}
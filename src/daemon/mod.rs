@@ -0,0 +1,9 @@
+pub mod runner;
+pub mod schedule_state;
+pub mod scheduler;
+pub mod watcher;
+
+pub use runner::*;
+pub use schedule_state::*;
+pub use scheduler::*;
+pub use watcher::*;
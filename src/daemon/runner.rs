@@ -0,0 +1,459 @@
+use crate::{
+    process_transfer_profile_with_context, AppConfig, CancellationToken, DueRun, FileWatcher,
+    LogProgress, MissedRunPolicy, OverlapPolicy, Profile, ScheduleState, Scheduler, SharedThrottle,
+    TransferContext, TransferOptions, TransferProfile, TransferReport, WatchFilter,
+    WatchedProfile,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use log::{error, info, warn};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::task::{Id, JoinSet};
+
+// 次の実行時刻までの待ち時間の上限（時計の変更に追従するため、定期的に計算し直す）
+// Upper bound on a single wait (recomputed periodically to follow changes to the clock)
+const MAX_IDLE: Duration = Duration::from_secs(60);
+
+/// config.yamlと、そこで指定されたプロファイルファイルを読み込む
+/// Load config.yaml and the profile file it points to
+pub fn load_configuration(config_path: Option<&str>) -> Result<(AppConfig, Profile)> {
+    let app_config = AppConfig::load(config_path)?;
+    let profile_path = app_config
+        .default_profile_file
+        .as_deref()
+        .context("No default profile file specified in the config.")?;
+    let profiles = Profile::load_profiles(Path::new(profile_path))
+        .with_context(|| format!("Failed to load profiles from '{}'", profile_path))?;
+    Ok((app_config, profiles))
+}
+
+/// スケジュール起動とファイル監視のプロファイルを実行し続ける。
+/// SIGHUPで設定を読み込み直し、SIGTERM（またはCtrl+C）で実行中の転送の完了を待って終了する。
+/// 終了待ちの間にもう一度シグナルを受けると、実行中の転送をキャンセルする。
+/// Keep running the scheduled and watched profiles.
+/// SIGHUP reloads the configuration; SIGTERM (or Ctrl+C) waits for running transfers and exits.
+/// Another signal while waiting cancels the running transfers.
+pub async fn run_daemon<F>(app_config: AppConfig, profiles: Profile, reload: F) -> Result<()>
+where
+    F: Fn() -> Result<(AppConfig, Profile)>,
+{
+    let mut signals = DaemonSignals::new()?;
+    let mut app_config = app_config;
+    let mut profiles = profiles;
+    let mut state_path = schedule_state_path(&app_config)?;
+    let mut state = load_state(&state_path);
+    let mut scheduler = Scheduler::new(&profiles.transfer_profiles, Local::now(), &state);
+    let mut watcher = FileWatcher::new(&profiles.transfer_profiles);
+    log_schedule(&scheduler, &watcher);
+
+    let mut dispatcher = Dispatcher::default();
+    dispatcher.update_global_throttle(&app_config);
+
+    loop {
+        let wait = scheduler
+            .next_run()
+            .map(|next| (next - Local::now()).to_std().unwrap_or(Duration::ZERO))
+            .unwrap_or(MAX_IDLE)
+            .min(MAX_IDLE);
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {
+                let now = Local::now();
+                for due in scheduler.take_due(now) {
+                    dispatcher.dispatch(&due, now, &profiles, &app_config);
+                    if let Some(run_at) = due.last_run_time() {
+                        state.record(&due.profile_id, run_at);
+                    }
+                    if let Err(e) = state.save(&state_path) {
+                        error!("Failed to save the schedule state: {:#}", e);
+                    }
+                }
+            }
+            ready = watcher.next_ready() => {
+                for profile_id in ready {
+                    if let Some(watched) = watcher.profile(&profile_id) {
+                        dispatcher.dispatch_watched(watched, &profiles, &app_config);
+                    }
+                }
+            }
+            Some(joined) = dispatcher.jobs.join_next_with_id(), if !dispatcher.jobs.is_empty() => {
+                dispatcher.finish(joined, Some((&profiles, &app_config)));
+            }
+            signal = signals.recv() => match signal {
+                DaemonSignal::Reload => {
+                    info!("Received SIGHUP. Reloading the configuration...");
+                    // 状態ファイルのパスも解決できてから新しい設定に切り替える
+                    // Switch to the new configuration only once its state file path resolves too
+                    let reloaded = reload().and_then(|(new_config, new_profiles)| {
+                        let new_state_path = schedule_state_path(&new_config)?;
+                        Ok((new_config, new_profiles, new_state_path))
+                    });
+                    match reloaded {
+                        Ok((new_config, new_profiles, new_state_path)) => {
+                            app_config = new_config;
+                            profiles = new_profiles;
+                            dispatcher.update_global_throttle(&app_config);
+                            if new_state_path != state_path {
+                                state_path = new_state_path;
+                                state = load_state(&state_path);
+                            }
+                            scheduler = Scheduler::new(&profiles.transfer_profiles, Local::now(), &state);
+                            watcher = FileWatcher::new(&profiles.transfer_profiles);
+                            log_schedule(&scheduler, &watcher);
+                        }
+                        Err(e) => {
+                            error!("Failed to reload the configuration, keeping the current one: {:#}", e);
+                        }
+                    }
+                }
+                DaemonSignal::Shutdown => break,
+            }
+        }
+    }
+
+    let queued: usize = dispatcher.queued.values().sum();
+    if queued > 0 {
+        warn!("Shutting down. Dropping {} queued run(s).", queued);
+    }
+    if !dispatcher.jobs.is_empty() {
+        info!(
+            "Shutting down. Waiting for {} running transfer(s) to finish (signal again to cancel them)...",
+            dispatcher.jobs.len()
+        );
+    }
+    loop {
+        tokio::select! {
+            joined = dispatcher.jobs.join_next_with_id() => match joined {
+                Some(joined) => dispatcher.finish(joined, None),
+                None => break,
+            },
+            _ = signals.recv(), if !dispatcher.shutdown.is_cancelled() => {
+                warn!("Cancelling running transfers...");
+                dispatcher.shutdown.cancel();
+            }
+        }
+    }
+    info!("Daemon stopped.");
+    Ok(())
+}
+
+fn schedule_state_path(app_config: &AppConfig) -> Result<PathBuf> {
+    match &app_config.schedule_state_file {
+        Some(path) => Ok(PathBuf::from(shellexpand::tilde(path).as_ref())),
+        None => ScheduleState::default_path(),
+    }
+}
+
+// 状態ファイルが読めない場合は、見逃した実行を判断できないだけなので空の状態で続ける
+// An unreadable state file only means missed runs cannot be detected, so continue with an empty state
+fn load_state(path: &Path) -> ScheduleState {
+    info!("Using schedule state file: {}", path.display());
+    ScheduleState::load(path).unwrap_or_else(|e| {
+        error!("{:#}. Missed runs will not be detected.", e);
+        ScheduleState::default()
+    })
+}
+
+/// 実行中・待機中のスケジュール実行
+/// Scheduled runs in progress and waiting
+#[derive(Default)]
+struct Dispatcher {
+    jobs: JoinSet<Result<TransferReport>>,
+    // 実行中のタスクと、そのプロファイルID
+    // Running tasks and their profile IDs
+    running: HashMap<Id, String>,
+    // 前回の実行の終了を待っている回数（プロファイルIDごと）
+    // Number of runs waiting for the previous one to finish, per profile ID
+    queued: HashMap<String, usize>,
+    shutdown: CancellationToken,
+    // config.yamlの全体の速度の上限。同時に実行されるすべての転送で共有する
+    // The global bandwidth cap from config.yaml, shared by every concurrent transfer
+    global_throttle: Option<SharedThrottle>,
+}
+
+impl Dispatcher {
+    /// 全体の速度の上限が変わった場合だけバケットを作り直す（実行中の転送と共有し続けるため）
+    /// Rebuild the bucket only when the global cap changed (so it stays shared with running transfers)
+    fn update_global_throttle(&mut self, app_config: &AppConfig) {
+        let current = self.global_throttle.as_ref().map(SharedThrottle::limit);
+        if current != app_config.bandwidth_limit.as_ref() {
+            self.global_throttle = app_config.bandwidth_limit.clone().map(SharedThrottle::new);
+        }
+    }
+
+    fn is_running(&self, profile_id: &str) -> bool {
+        self.running.values().any(|running_id| running_id == profile_id)
+    }
+
+    /// 実行時刻を迎えたプロファイルを、missedRuns と overlap に従って実行・待機・スキップする
+    /// Run, queue or skip a due profile according to its missedRuns and overlap policies
+    fn dispatch(
+        &mut self,
+        due: &DueRun,
+        now: DateTime<Local>,
+        profiles: &Profile,
+        app_config: &AppConfig,
+    ) {
+        let plan = due.plan(now);
+        if plan.missed > 0 {
+            let decision = match due.missed_runs {
+                MissedRunPolicy::Skip => "skipping them",
+                MissedRunPolicy::RunOnce => "running once",
+                MissedRunPolicy::RunAll => "running each of them",
+            };
+            info!(
+                "Profile '{}' missed {} run(s) since {} (missedRuns: {}): {}.",
+                due.profile_id,
+                plan.missed,
+                due.run_times[0].format("%Y-%m-%d %H:%M:%S"),
+                due.missed_runs,
+                decision
+            );
+        }
+        if plan.runs == 0 {
+            return;
+        }
+        let Some(profile) = find_profile(profiles, &due.profile_id) else {
+            return;
+        };
+
+        // 2回目以降（見逃した実行の消化）は常に順番に実行する
+        // Any further runs (catching up on missed ones) always run one after another
+        let mut waiting = plan.runs - 1;
+        if !self.is_running(&due.profile_id) || due.overlap == OverlapPolicy::Allow {
+            if self.is_running(&due.profile_id) {
+                info!(
+                    "Profile '{}' is still running; starting another run alongside it (overlap: allow).",
+                    due.profile_id
+                );
+            }
+            self.start(profile, app_config, due.overlap);
+        } else if due.overlap == OverlapPolicy::Queue {
+            info!(
+                "Profile '{}' is still running; the run will start when it finishes (overlap: queue).",
+                due.profile_id
+            );
+            waiting += 1;
+        } else {
+            warn!(
+                "Skipping {} run(s) of profile '{}': the previous run is still in progress (overlap: skip).",
+                plan.runs, due.profile_id
+            );
+            return;
+        }
+        if waiting > 0 {
+            *self.queued.entry(due.profile_id.clone()).or_default() += waiting;
+            info!(
+                "Queued {} run(s) of profile '{}' to start one after another.",
+                waiting, due.profile_id
+            );
+        }
+    }
+
+    /// ファイルが置かれたプロファイルを overlap に従って実行・待機・スキップする。
+    /// 次の実行でその時点のファイルをすべて転送するため、待機は1回分にまとめる。
+    /// Run, queue or skip a profile whose watched files arrived according to its overlap policy.
+    /// The next run picks up every file present at that point, so at most one run is queued.
+    fn dispatch_watched(&mut self, watched: &WatchedProfile, profiles: &Profile, app_config: &AppConfig) {
+        let Some(profile) = find_profile(profiles, &watched.profile_id) else {
+            return;
+        };
+        if !self.is_running(&watched.profile_id) || watched.overlap == OverlapPolicy::Allow {
+            if self.is_running(&watched.profile_id) {
+                info!(
+                    "Profile '{}' is still running; starting another run alongside it (overlap: allow).",
+                    watched.profile_id
+                );
+            }
+            self.start(profile, app_config, watched.overlap);
+        } else if watched.overlap == OverlapPolicy::Queue {
+            if !self.queued.contains_key(&watched.profile_id) {
+                info!(
+                    "Profile '{}' is still running; it will run again when it finishes (overlap: queue).",
+                    watched.profile_id
+                );
+                self.queued.insert(watched.profile_id.clone(), 1);
+            }
+        } else {
+            warn!(
+                "Skipping a run of profile '{}': the previous run is still in progress (overlap: skip).",
+                watched.profile_id
+            );
+        }
+    }
+
+    fn start(&mut self, profile: &TransferProfile, app_config: &AppConfig, overlap: OverlapPolicy) {
+        info!("Starting transfer for profile '{}'.", profile.profile_id);
+        let task = self.jobs.spawn(run_scheduled_profile(
+            profile.clone(),
+            app_config.clone(),
+            overlap,
+            self.global_throttle.clone(),
+            self.shutdown.child_token(),
+        ));
+        self.running.insert(task.id(), profile.profile_id.clone());
+    }
+
+    /// 終了したタスクの結果を記録し、待機中の実行があれば開始する（終了処理中は `current` がNone）
+    /// Log a finished task and start a queued run if any (`current` is None while shutting down)
+    fn finish(
+        &mut self,
+        joined: std::result::Result<(Id, Result<TransferReport>), tokio::task::JoinError>,
+        current: Option<(&Profile, &AppConfig)>,
+    ) {
+        let profile_id = match joined {
+            Ok((id, result)) => {
+                let profile_id = self.running.remove(&id).unwrap_or_default();
+                match result {
+                    Ok(report) => info!("{}", report),
+                    Err(e) => error!("Scheduled transfer for profile '{}' failed: {:#}", profile_id, e),
+                }
+                profile_id
+            }
+            Err(e) => {
+                let profile_id = self.running.remove(&e.id()).unwrap_or_default();
+                error!("Scheduled transfer for profile '{}' terminated unexpectedly: {}", profile_id, e);
+                profile_id
+            }
+        };
+
+        let Some((profiles, app_config)) = current else {
+            return;
+        };
+        let Some(waiting) = self.queued.get_mut(&profile_id) else {
+            return;
+        };
+        *waiting -= 1;
+        if *waiting == 0 {
+            self.queued.remove(&profile_id);
+        }
+        match find_profile(profiles, &profile_id) {
+            Some(profile) => self.start(profile, app_config, OverlapPolicy::Queue),
+            None => {
+                self.queued.remove(&profile_id);
+            }
+        }
+    }
+}
+
+fn find_profile<'a>(profiles: &'a Profile, profile_id: &str) -> Option<&'a TransferProfile> {
+    let profile = profiles
+        .transfer_profiles
+        .iter()
+        .find(|profile| profile.profile_id == profile_id);
+    if profile.is_none() {
+        warn!("Profile '{}' is no longer defined; skipping its runs.", profile_id);
+    }
+    profile
+}
+
+async fn run_scheduled_profile(
+    profile: TransferProfile,
+    app_config: AppConfig,
+    overlap: OverlapPolicy,
+    global_throttle: Option<SharedThrottle>,
+    cancel: CancellationToken,
+) -> Result<TransferReport> {
+    // overlap: allow では同時実行を許可しているため、プロファイルのロックを取得しない
+    // overlap: allow permits concurrent runs, so the profile lock is not taken
+    let options = TransferOptions {
+        profile_lock: overlap != OverlapPolicy::Allow,
+        ..TransferOptions::from_app_config(&app_config)
+    };
+    let context = TransferContext::new(cancel)
+        .with_progress(Arc::new(LogProgress::default()))
+        .with_options(options)
+        .with_global_throttle(global_throttle);
+    process_transfer_profile_with_context(profile, context).await
+}
+
+fn log_schedule(scheduler: &Scheduler, watcher: &FileWatcher) {
+    if scheduler.is_empty() && watcher.is_empty() {
+        warn!("No profiles with a schedule or watch trigger. Waiting for a reload (SIGHUP)...");
+        return;
+    }
+    for watched in watcher.profiles() {
+        let target = match &watched.filter {
+            WatchFilter::File(name) => watched.root.join(name),
+            _ => watched.root.clone(),
+        };
+        info!(
+            "Watching '{}' for profile '{}' (debounce: {}ms).",
+            target.display(),
+            watched.profile_id,
+            watched.debounce.as_millis()
+        );
+    }
+    if scheduler.is_empty() {
+        return;
+    }
+    let mut entries: Vec<_> = scheduler.entries().iter().collect();
+    entries.sort_by_key(|entry| entry.next_run);
+    info!("Upcoming schedule:");
+    for entry in entries {
+        match entry.next_run {
+            Some(next) => info!(
+                "  {} - profile '{}' ({})",
+                next.format("%Y-%m-%d %H:%M:%S"),
+                entry.profile_id,
+                entry.schedule.source()
+            ),
+            None => info!(
+                "  (no further runs) - profile '{}' ({})",
+                entry.profile_id,
+                entry.schedule.source()
+            ),
+        }
+    }
+}
+
+enum DaemonSignal {
+    Reload,
+    Shutdown,
+}
+
+// SIGHUP / SIGTERM / Ctrl+C を待ち受ける（Unix以外ではCtrl+Cのみ）
+// Listens for SIGHUP / SIGTERM / Ctrl+C (only Ctrl+C outside Unix)
+struct DaemonSignals {
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+}
+
+impl DaemonSignals {
+    #[cfg(unix)]
+    fn new() -> Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(DaemonSignals {
+            hangup: signal(SignalKind::hangup()).context("Failed to listen for SIGHUP")?,
+            terminate: signal(SignalKind::terminate()).context("Failed to listen for SIGTERM")?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> Result<Self> {
+        Ok(DaemonSignals {})
+    }
+
+    async fn recv(&mut self) -> DaemonSignal {
+        #[cfg(unix)]
+        {
+            tokio::select! {
+                _ = self.hangup.recv() => DaemonSignal::Reload,
+                _ = self.terminate.recv() => DaemonSignal::Shutdown,
+                _ = tokio::signal::ctrl_c() => DaemonSignal::Shutdown,
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+            DaemonSignal::Shutdown
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use etcetera::{choose_base_strategy, BaseStrategy};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// デーモンが処理したプロファイルごとの最終実行時刻。再起動後に見逃した実行時刻を判断するために保存する。
+/// Last run time handled by the daemon per profile. Persisted to find missed run times after a restart.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleState {
+    pub last_runs: BTreeMap<String, DateTime<Local>>,
+}
+
+impl ScheduleState {
+    /// 既定の保存先（データディレクトリの vento/schedule_state.json）
+    /// Default location (vento/schedule_state.json in the data directory)
+    pub fn default_path() -> Result<PathBuf> {
+        let strategy = choose_base_strategy().context("Unable to find the data directory!")?;
+        Ok(strategy.data_dir().join("vento").join("schedule_state.json"))
+    }
+
+    /// 状態ファイルを読み込む（存在しない場合は空の状態）
+    /// Load the state file (an empty state when it does not exist)
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(ScheduleState::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read schedule state: '{}'", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse schedule state: '{}'", path.display()))
+    }
+
+    /// 途中で中断しても壊れないよう、一時ファイルに書いてからリネームする
+    /// Write to a temporary file and rename it, so an interrupted write never corrupts the state
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory: '{}'", dir.display()))?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write schedule state: '{}'", temp_path.display()))?;
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to write schedule state: '{}'", path.display()))
+    }
+
+    pub fn last_run(&self, profile_id: &str) -> Option<DateTime<Local>> {
+        self.last_runs.get(profile_id).copied()
+    }
+
+    pub fn record(&mut self, profile_id: &str, run_at: DateTime<Local>) {
+        self.last_runs.insert(profile_id.to_string(), run_at);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{MissedRunPolicy, OverlapPolicy, ScheduleState, TransferProfile};
+use chrono::{DateTime, Local, TimeDelta};
+use log::warn;
+
+// 実行時刻からこの時間以上遅れて気付いた場合は、見逃した実行として扱う
+// A run time noticed this much later or more is treated as missed
+pub const MISSED_RUN_GRACE: TimeDelta = TimeDelta::seconds(60);
+// 一度に遡る実行時刻の上限（長期間停止していた場合の歯止め）
+// Upper bound on the run times caught up at once (a safeguard after a long outage)
+const MAX_CATCH_UP_RUNS: usize = 1000;
+
+/// スケジュール起動のプロファイルと、その次回の実行時刻
+/// A scheduled profile and its next run time
+#[derive(Debug, Clone)]
+pub struct ScheduledProfile {
+    pub profile_id: String,
+    pub schedule: cron::Schedule,
+    pub missed_runs: MissedRunPolicy,
+    pub overlap: OverlapPolicy,
+    // Cron式にこれ以降の実行時刻がない場合はNone
+    // None when the cron expression has no further run times
+    pub next_run: Option<DateTime<Local>>,
+}
+
+/// 実行時刻を迎えたプロファイル
+/// A profile whose run time has arrived
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DueRun {
+    pub profile_id: String,
+    pub missed_runs: MissedRunPolicy,
+    pub overlap: OverlapPolicy,
+    // 迎えた実行時刻（古い順）
+    // The run times that arrived, oldest first
+    pub run_times: Vec<DateTime<Local>>,
+}
+
+/// 迎えた実行時刻のうち、実際に実行する回数
+/// How many of the arrived run times are actually run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunPlan {
+    pub runs: usize,
+    // 見逃した実行時刻の数
+    // Number of missed run times
+    pub missed: usize,
+}
+
+impl DueRun {
+    /// `missed_runs` に従って実行回数を決める。
+    /// `now` から `MISSED_RUN_GRACE` 以上前の実行時刻は見逃したものとして扱う。
+    /// Decide the number of runs according to `missed_runs`.
+    /// Run times `MISSED_RUN_GRACE` or more before `now` are treated as missed.
+    pub fn plan(&self, now: DateTime<Local>) -> RunPlan {
+        let missed = self
+            .run_times
+            .iter()
+            .filter(|run_at| now - **run_at >= MISSED_RUN_GRACE)
+            .count();
+        let on_time = self.run_times.len() > missed;
+        let runs = match self.missed_runs {
+            MissedRunPolicy::Skip => on_time as usize,
+            MissedRunPolicy::RunOnce => (on_time || missed > 0) as usize,
+            MissedRunPolicy::RunAll => missed + on_time as usize,
+        };
+        RunPlan { runs, missed }
+    }
+
+    pub fn last_run_time(&self) -> Option<DateTime<Local>> {
+        self.run_times.last().copied()
+    }
+}
+
+/// `trigger.type: schedule` のプロファイルの実行時刻を管理する
+/// Keeps track of the run times of profiles with `trigger.type: schedule`
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    entries: Vec<ScheduledProfile>,
+}
+
+impl Scheduler {
+    /// スケジュール起動のプロファイルを集め、次回の実行時刻を計算する。
+    /// `state` に最終実行時刻があるプロファイルはその直後から、ないものは `now` の後から数える。
+    /// Cron式が不正なプロファイルは警告を出して除外する。
+    /// Collect the scheduled profiles and compute their next run times.
+    /// Profiles with a last run time in `state` count from just after it, the others from after `now`.
+    /// Profiles with an invalid cron expression are skipped with a warning.
+    pub fn new(profiles: &[TransferProfile], now: DateTime<Local>, state: &ScheduleState) -> Self {
+        let entries = profiles
+            .iter()
+            .filter_map(|profile| match profile.source.trigger.cron_schedule() {
+                Ok(Some(schedule)) => {
+                    let since = state
+                        .last_run(&profile.profile_id)
+                        .filter(|last_run| *last_run < now)
+                        .unwrap_or(now);
+                    Some(ScheduledProfile {
+                        profile_id: profile.profile_id.clone(),
+                        missed_runs: profile.source.trigger.missed_runs.unwrap_or_default(),
+                        overlap: profile.source.trigger.overlap.unwrap_or_default(),
+                        next_run: schedule.after(&since).next(),
+                        schedule,
+                    })
+                }
+                Ok(None) => None,
+                Err(e) => {
+                    warn!("Skipping profile '{}': {}", profile.profile_id, e);
+                    None
+                }
+            })
+            .collect();
+        Scheduler { entries }
+    }
+
+    pub fn entries(&self) -> &[ScheduledProfile] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 最も近い実行時刻
+    /// The earliest upcoming run time
+    pub fn next_run(&self) -> Option<DateTime<Local>> {
+        self.entries.iter().filter_map(|entry| entry.next_run).min()
+    }
+
+    /// `now` までに迎えた実行時刻をプロファイルごとに返し、次回の実行時刻を `now` の後へ進める
+    /// Return the run times that arrived by `now` per profile and move the next run past `now`
+    pub fn take_due(&mut self, now: DateTime<Local>) -> Vec<DueRun> {
+        let mut due = Vec::new();
+        for entry in &mut self.entries {
+            let Some(next_run) = entry.next_run.filter(|next| *next <= now) else {
+                continue;
+            };
+            let mut run_times = vec![next_run];
+            run_times.extend(
+                entry
+                    .schedule
+                    .after(&next_run)
+                    .take_while(|run_at| *run_at <= now)
+                    .take(MAX_CATCH_UP_RUNS - 1),
+            );
+            entry.next_run = entry.schedule.after(&now).next();
+            due.push(DueRun {
+                profile_id: entry.profile_id.clone(),
+                missed_runs: entry.missed_runs,
+                overlap: entry.overlap,
+                run_times,
+            });
+        }
+        due
+    }
+}
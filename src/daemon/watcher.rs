@@ -0,0 +1,324 @@
+use crate::{AppError, FileMatcher, FileSelection, OverlapPolicy, TransferProfile, TriggerType};
+use log::{error, warn};
+use notify::{
+    event::{AccessKind, AccessMode, CreateKind, ModifyKind, RenameMode},
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+// 最後のファイルイベントから実行までに待つ既定の時間（ミリ秒）
+// Default time to wait after the last file event before running (milliseconds)
+pub const DEFAULT_WATCH_DEBOUNCE_MS: u64 = 1000;
+
+/// 監視対象のファイルの選び方
+/// How the watched files are selected
+#[derive(Debug, Clone)]
+pub enum WatchFilter {
+    // ディレクトリ内のすべてのファイル
+    // Every file in the directory
+    All,
+    // 転送元のglobやfilterに一致するファイル
+    // Files matching the source glob or filter
+    Matching(FileMatcher),
+    // 転送元が単一ファイルの場合、そのファイル名
+    // The file name when the source is a single file
+    File(OsString),
+}
+
+/// `trigger.type: watch` のプロファイルと、その監視対象
+/// A profile with `trigger.type: watch` and what it watches
+#[derive(Debug, Clone)]
+pub struct WatchedProfile {
+    pub profile_id: String,
+    pub root: PathBuf,
+    pub recursive: bool,
+    pub filter: WatchFilter,
+    pub debounce: Duration,
+    // 未指定の場合は queue（実行中に置かれたファイルを取りこぼさないため）
+    // queue when omitted (so files dropped during a run are not missed)
+    pub overlap: OverlapPolicy,
+    // stability.triggerFileSuffix が指定されている場合、トリガーファイルの到着でも実行する
+    // With stability.triggerFileSuffix set, the arrival of a trigger file also runs the profile
+    pub trigger_file_suffix: Option<String>,
+}
+
+impl WatchedProfile {
+    /// 監視対象を転送元の設定から組み立てる（watch トリガー以外はNone）
+    /// Build the watch target from the source settings (None for other triggers)
+    pub fn from_profile(profile: &TransferProfile) -> Result<Option<Self>, AppError> {
+        let trigger = &profile.source.trigger;
+        if trigger.kind != TriggerType::Watch {
+            return Ok(None);
+        }
+        let recursive = profile.recursive.unwrap_or(false);
+        let path = PathBuf::from(&profile.source.path);
+        let (root, filter) = match FileSelection::from_source(&profile.source)? {
+            Some(selection) => (selection.root, WatchFilter::Matching(selection.matcher)),
+            None if path.is_dir() => (path, WatchFilter::All),
+            None => {
+                let name = path.file_name().map(OsString::from).ok_or_else(|| {
+                    AppError::Validation(format!(
+                        "Cannot watch source path '{}'",
+                        profile.source.path
+                    ))
+                })?;
+                let parent = path
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                    .unwrap_or_else(|| Path::new("."));
+                (parent.to_path_buf(), WatchFilter::File(name))
+            }
+        };
+        Ok(Some(WatchedProfile {
+            profile_id: profile.profile_id.clone(),
+            root,
+            recursive,
+            filter,
+            debounce: trigger.debounce(),
+            overlap: trigger.overlap.unwrap_or(OverlapPolicy::Queue),
+            trigger_file_suffix: profile
+                .source
+                .stability
+                .as_ref()
+                .and_then(|stability| stability.trigger_file_suffix.clone()),
+        }))
+    }
+
+    /// ファイルが監視対象に含まれるかどうか
+    /// Whether a file is one of the watched files
+    pub fn matches(&self, path: &Path) -> bool {
+        // トリガーファイルは対応するファイルのパスで判定する
+        // A trigger file is judged by the path of the file it goes with
+        let data_file = self.trigger_file_suffix.as_ref().and_then(|suffix| {
+            path.to_str()
+                .and_then(|path| path.strip_suffix(suffix.as_str()))
+                .map(PathBuf::from)
+        });
+        let path = data_file.as_deref().unwrap_or(path);
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let depth = relative.components().count();
+        if depth == 0 || (!self.recursive && depth > 1) {
+            return false;
+        }
+        match &self.filter {
+            WatchFilter::All => true,
+            WatchFilter::Matching(matcher) => matcher.is_match(relative),
+            WatchFilter::File(name) => relative.as_os_str() == name,
+        }
+    }
+}
+
+/// 新しく置かれたファイルを示すイベントかどうか。
+/// 作成・書き込み後のクローズ・リネームによる移動を対象とする。
+/// Whether an event signals a newly dropped file.
+/// Covers creation, close after writing and renames into place.
+pub fn is_file_drop(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(CreateKind::File | CreateKind::Any)
+            | EventKind::Access(AccessKind::Close(AccessMode::Write))
+            | EventKind::Modify(ModifyKind::Name(
+                RenameMode::To | RenameMode::Both | RenameMode::Any
+            ))
+    )
+}
+
+/// プロファイルごとのデバウンス。イベントのたびに期限を延ばし、期限を過ぎたものを返す。
+/// Per-profile debouncing. Every event pushes the deadline back; expired ones are handed out.
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    deadlines: HashMap<String, Instant>,
+}
+
+impl Debouncer {
+    pub fn touch(&mut self, profile_id: &str, now: Instant, debounce: Duration) {
+        self.deadlines.insert(profile_id.to_string(), now + debounce);
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.deadlines.values().min().copied()
+    }
+
+    /// 期限を過ぎたプロファイルIDを取り出す
+    /// Take the profile IDs whose deadline has passed
+    pub fn take_ready(&mut self, now: Instant) -> Vec<String> {
+        let mut ready: Vec<String> = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(profile_id, _)| profile_id.clone())
+            .collect();
+        ready.sort();
+        for profile_id in &ready {
+            self.deadlines.remove(profile_id);
+        }
+        ready
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deadlines.is_empty()
+    }
+}
+
+/// watch トリガーのプロファイルの転送元ディレクトリを監視する
+/// Watches the source directories of the profiles with a watch trigger
+pub struct FileWatcher {
+    profiles: Vec<WatchedProfile>,
+    debouncer: Debouncer,
+    events: mpsc::UnboundedReceiver<notify::Result<Event>>,
+    // ドロップすると監視が止まるため保持する（監視対象がない場合はNone）
+    // Kept alive since dropping it stops watching (None when nothing is watched)
+    _watcher: Option<RecommendedWatcher>,
+}
+
+impl FileWatcher {
+    /// watch トリガーのプロファイルの監視を開始する。
+    /// 監視を開始できなかったプロファイルはエラーを出力して除外する。
+    /// Start watching for the profiles with a watch trigger.
+    /// Profiles that cannot be watched are skipped with an error.
+    pub fn new(profiles: &[TransferProfile]) -> Self {
+        let (sender, events) = mpsc::unbounded_channel();
+        let mut watched: Vec<WatchedProfile> = profiles
+            .iter()
+            .filter_map(|profile| match WatchedProfile::from_profile(profile) {
+                Ok(watched) => watched,
+                Err(e) => {
+                    warn!("Skipping profile '{}': {}", profile.profile_id, e);
+                    None
+                }
+            })
+            .collect();
+        if watched.is_empty() {
+            return FileWatcher::with_profiles(Vec::new(), events, None);
+        }
+
+        let mut watcher = match notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        }) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!("Failed to start the file watcher; watch triggers are disabled: {}", e);
+                return FileWatcher::with_profiles(Vec::new(), events, None);
+            }
+        };
+        watched.retain(|profile| {
+            let mode = if profile.recursive {
+                RecursiveMode::Recursive
+            } else {
+                RecursiveMode::NonRecursive
+            };
+            match watcher.watch(&profile.root, mode) {
+                Ok(()) => true,
+                Err(e) => {
+                    error!(
+                        "Skipping profile '{}': failed to watch '{}': {}",
+                        profile.profile_id,
+                        profile.root.display(),
+                        e
+                    );
+                    false
+                }
+            }
+        });
+        FileWatcher::with_profiles(watched, events, Some(watcher))
+    }
+
+    fn with_profiles(
+        profiles: Vec<WatchedProfile>,
+        events: mpsc::UnboundedReceiver<notify::Result<Event>>,
+        watcher: Option<RecommendedWatcher>,
+    ) -> Self {
+        FileWatcher {
+            profiles,
+            debouncer: Debouncer::default(),
+            events,
+            _watcher: watcher,
+        }
+    }
+
+    pub fn profiles(&self) -> &[WatchedProfile] {
+        &self.profiles
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    pub fn profile(&self, profile_id: &str) -> Option<&WatchedProfile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.profile_id == profile_id)
+    }
+
+    /// ファイルイベントを監視対象のプロファイルに振り分け、デバウンスの期限を延ばす
+    /// Route a file event to the watching profiles and push back their debounce deadline
+    pub fn handle_event(&mut self, event: &Event, now: Instant) {
+        // イベントの取りこぼしが起きた場合は、すべてのプロファイルを実行する
+        // When events were dropped, run every profile
+        if event.need_rescan() {
+            warn!("File events were dropped; running every watched profile.");
+            for profile in &self.profiles {
+                self.debouncer.touch(&profile.profile_id, now, profile.debounce);
+            }
+            return;
+        }
+        if !is_file_drop(&event.kind) {
+            return;
+        }
+        // リネームの場合は移動先のパスだけを見る
+        // For renames only the destination path counts
+        let skip = match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => event.paths.len().saturating_sub(1),
+            _ => 0,
+        };
+        for profile in &self.profiles {
+            if event.paths.iter().skip(skip).any(|path| profile.matches(path)) {
+                self.debouncer.touch(&profile.profile_id, now, profile.debounce);
+            }
+        }
+    }
+
+    /// デバウンスの期限を過ぎたプロファイルIDを取り出す
+    /// Take the profile IDs whose debounce deadline has passed
+    pub fn take_ready(&mut self, now: Instant) -> Vec<String> {
+        self.debouncer.take_ready(now)
+    }
+
+    /// デバウンスの期限を迎えたプロファイルIDを返すまで待つ（監視対象がなければ待ち続ける）。
+    /// `tokio::select!` で中断しても、受け取ったイベントは失われない。
+    /// Wait until some profiles reach their debounce deadline and return their IDs (pending forever without watches).
+    /// Safe to cancel in `tokio::select!`; events already received are not lost.
+    pub async fn next_ready(&mut self) -> Vec<String> {
+        loop {
+            let deadline = self.debouncer.next_deadline();
+            tokio::select! {
+                event = self.events.recv(), if !self.profiles.is_empty() => match event {
+                    Some(Ok(event)) => self.handle_event(&event, Instant::now()),
+                    Some(Err(e)) => error!("File watcher error: {}", e),
+                    None => std::future::pending::<()>().await,
+                },
+                _ = sleep_until(deadline), if deadline.is_some() => {
+                    let ready = self.take_ready(Instant::now());
+                    if !ready.is_empty() {
+                        return ready;
+                    }
+                }
+                else => std::future::pending::<()>().await,
+            }
+        }
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    if let Some(deadline) = deadline {
+        tokio::time::sleep_until(deadline.into()).await;
+    }
+}
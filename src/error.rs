@@ -1,6 +1,10 @@
 use cron::error::Error as CronError;
 use thiserror::Error;
 
+// 同じプロファイルを別のプロセスが実行中で終了した場合の終了コード（sysexits.hのEX_TEMPFAIL）
+// Exit code when another process is running the same profile (EX_TEMPFAIL from sysexits.h)
+pub const EXIT_PROFILE_LOCKED: i32 = 75;
+
 #[derive(Debug, Error)]
 pub enum AppError {
     #[error("I/O error: {0}")]
@@ -42,10 +46,77 @@ pub enum AppError {
 
     #[error("Private key reference is missing in the authentication config.")]
     MissingPrivateKeyReference,
+
+    #[error("Checksum mismatch for '{path}' ({method}): local={local}, remote={remote}")]
+    // 転送後の検証で転送元と転送先の内容が一致しなかった
+    // Post-transfer verification found that source and destination differ
+    ChecksumMismatch {
+        path: String,
+        method: String,
+        local: String,
+        remote: String,
+    },
+    #[error("File '{path}' exceeds max allowed size ({limit_mb} MB)")]
+    // 転送対象ファイルがサイズ上限を超えている
+    // The file to transfer is larger than the configured size limit
+    FileTooLarge { path: String, limit_mb: u64 },
+
+    #[error("Timed out after {secs}s while {operation}")]
+    // 接続・ハンドシェイク・転送操作がタイムアウトした
+    // The connect, the handshake or a transfer operation timed out
+    Timeout { operation: String, secs: u64 },
+
+    #[error("Host key verification failed for {host}: {reason}")]
+    // サーバーのホスト鍵をknown_hostsまたは固定フィンガープリントで確認できなかった
+    // The server's host key could not be confirmed against known_hosts or the pinned fingerprint
+    HostKeyVerification { host: String, reason: String },
+
+    #[error("{job_type} command failed for profile '{profile_id}'")]
+    // 転送前後のフックコマンドが0以外の終了コードで終了した（シグナルで終了した場合はコードなし）
+    // A hook command exited with a non-zero code (no code when it was killed by a signal)
+    CommandFailed {
+        job_type: String,
+        profile_id: String,
+        code: Option<i32>,
+    },
+
+    #[error("Transfer was cancelled")]
+    // キャンセル用トークンにより転送が中断された
+    // The transfer was stopped through its cancellation token
+    Cancelled,
+
+    #[error("Source file '{path}' is not ready for transfer: {reason}")]
+    // 転送元ファイルが書き込み中か、トリガーファイルがない
+    // The source file is still being written, or its trigger file is missing
+    SourceNotReady { path: String, reason: String },
+
+    #[error(
+        "Profile '{profile_id}' is already running (pid: {}); lock file: {path}",
+        .pid.map_or_else(|| "unknown".to_string(), |pid| pid.to_string())
+    )]
+    // 同じプロファイルを別のプロセスが実行中で、ロックを取得できなかった
+    // Another process is running the same profile and holds its lock
+    ProfileLocked {
+        profile_id: String,
+        pid: Option<u32>,
+        path: String,
+    },
+
     //... 他の具体的なエラー
     // Other specific errors
 }
 
+impl AppError {
+    /// このエラーで終了する場合のプロセスの終了コード
+    /// Process exit code when exiting with this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::ProfileLocked { .. } => EXIT_PROFILE_LOCKED,
+            _ => 1,
+        }
+    }
+}
+
 // anyhow::Error から AppError::Validation への変換は既存のままでOK
 // The conversion from anyhow::Error to AppError::Validation can be left as is.
 impl From<anyhow::Error> for AppError {
@@ -1,50 +1,34 @@
 pub mod admin;
 pub mod cli;
 pub mod config;
+pub mod daemon;
 pub mod error;
 pub mod profile;
 pub mod transfer;
 pub mod util;
 
-use std::sync::OnceLock;
-use std::sync::atomic::{AtomicU64, Ordering};
 pub use admin::*;
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result};
 pub use cli::*;
 pub use config::*;
+pub use daemon::*;
 pub use error::*;
 use log::LevelFilter;
 pub use profile::*;
 pub use transfer::*;
 pub use util::*;
 
-pub static MAX_FILE_SIZE_MB: OnceLock<AtomicU64> = OnceLock::new();
-pub const MAX_ALLOWED_MB: usize = 2048; // 2GB
+// config.yamlで max_file_size_mb が未指定の場合の上限（0を指定すると無制限）
+// Limit used when max_file_size_mb is not set in config.yaml (0 means unlimited)
+pub const DEFAULT_MAX_FILE_SIZE_MB: u64 = 500;
 
-pub fn init_max_file_size_mb(val: u64) -> Result<()> {
-    if val as usize > MAX_ALLOWED_MB {
-        return Err(anyhow!(
-            "max_file_size_mb must not exceed {}MB (got: {}MB)",
-            MAX_ALLOWED_MB,
-            val
-        ));
-    }
-
-    MAX_FILE_SIZE_MB
-        .set(AtomicU64::new(val))
-        .map_err(|_| anyhow!("MAX_FILE_SIZE_MB is already initialized"))?;
-
-    Ok(())
-}
-
-pub fn get_max_file_size_mb() -> u64 {
-    MAX_FILE_SIZE_MB
-        .get()
-        .expect("MAX_FILE_SIZE_MB not initialized")
-        .load(Ordering::Relaxed)
+pub fn setup_logging(app_config: &AppConfig) -> Result<()> {
+    setup_logging_with_console(app_config, false)
 }
 
-pub fn setup_logging(app_config: &AppConfig) -> Result<()> {
+/// `to_stderr` がtrueの場合、コンソールへのログを標準エラー出力に書き出す（標準出力をJSONなどに使う場合）
+/// When `to_stderr` is true, console logs go to stderr (for when stdout carries JSON and the like)
+pub fn setup_logging_with_console(app_config: &AppConfig, to_stderr: bool) -> Result<()> {
     let level = app_config.log_level.as_deref().unwrap_or("info");
     let log_level = match level.to_lowercase().as_str() {
         "error" => LevelFilter::Error,
@@ -69,7 +53,11 @@ pub fn setup_logging(app_config: &AppConfig) -> Result<()> {
 
     let should_log_to_stdout = app_config.log_stdout.unwrap_or(true); // Set default to true
     if should_log_to_stdout {
-        base_config = base_config.chain(std::io::stdout());
+        base_config = if to_stderr {
+            base_config.chain(std::io::stderr())
+        } else {
+            base_config.chain(std::io::stdout())
+        };
     }
 
     // ログファイル出力が指定されている場合
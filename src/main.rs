@@ -1,23 +1,15 @@
 use std::path::Path;
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::Parser;
 use log::{error, info};
-use vento::{dispatch, init_max_file_size_mb, setup_logging, AppConfig, Cli, Profile};
+use vento::{dispatch, setup_logging_with_console, AppConfig, AppError, Cli, Profile};
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    let config_path = &cli.config;
-
     // `--config` オプションが指定された場合は、参照するconfigファイルをオーバーライドする
     // If the `--config` option is specified, it overrides the config file referenced.
-    let app_config: AppConfig = if let Some(path) = config_path {
-        AppConfig::over_ride_config(path)?
-    } else {
-        AppConfig::load_config().context("Failed to load default application configuration")?
-    };
-
-    init_max_file_size_mb(app_config.max_file_size_mb.unwrap_or(500))?;
+    let app_config = AppConfig::load(cli.config.as_deref())?;
 
     let profile_path = &app_config.default_profile_file;
     if profile_path.is_none() {
@@ -25,7 +17,7 @@ async fn main() -> Result<()> {
         std::process::exit(1)
     }
 
-    setup_logging(&app_config)?;
+    setup_logging_with_console(&app_config, cli.json_output())?;
 
     let profile_path = profile_path.as_ref().unwrap();
     info!("Using profile file: {}", profile_path);
@@ -35,6 +27,11 @@ async fn main() -> Result<()> {
 
     if let Err(e) = &result {
         error!("Application error: {:?}", e);
+        // 同じプロファイルの実行中はcronなどの呼び出し元が区別できるよう専用の終了コードで終了する
+        // Exit with a dedicated code while the profile is already running, so callers such as cron can tell
+        if let Some(app_error @ AppError::ProfileLocked { .. }) = e.downcast_ref::<AppError>() {
+            std::process::exit(app_error.exit_code());
+        }
     }
 
     result
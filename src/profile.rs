@@ -1,10 +1,10 @@
-use std::{fs, path::Path, str::FromStr};
+use std::{fmt, fs, path::Path, str::FromStr, time::Duration};
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
-use crate::{validate_ascii, validate_cross_platform_path, AppError};
+use crate::{ensure_passphrase_available, get_private_key_path, validate_ascii, validate_cross_platform_path, validate_source_path, AppError, FileSelection, DEFAULT_WATCH_DEBOUNCE_MS, HostKeySettings, TimeoutSettings};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -44,6 +44,42 @@ pub struct TransferProfile {
     #[validate(length(min = 1, max = 256))]
     #[validate(custom(function = "validate_ascii"))]
     pub on_error_command: Option<String>,
+
+    // source.pathがディレクトリの場合にツリーごと転送する
+    // Transfer the whole tree when source.path is a directory
+    pub recursive: Option<bool>,
+
+    // SFTP転送が中断した場合に、転送済みの位置から再開する
+    // Resume an interrupted SFTP transfer from where it stopped
+    pub resume: Option<bool>,
+
+    // 転送後の検証方法（sha256 / md5 / size）
+    // Post-transfer verification method (sha256 / md5 / size)
+    pub verify: Option<VerifyMethod>,
+
+    // 一時ファイルに書き込み、完了後にリネームして配置する
+    // Write to a temporary file and rename it into place once complete
+    pub atomic: Option<AtomicDelivery>,
+
+    // 転送失敗時の再試行設定
+    // Retry settings for failed transfers
+    pub retry: Option<RetryPolicy>,
+
+    // 接続・操作のタイムアウト（未設定の項目はconfig.yamlの値を使う）
+    // Connection and operation timeouts (unset fields fall back to config.yaml)
+    pub timeouts: Option<TimeoutSettings>,
+
+    // ホスト鍵の検証設定（未設定の項目はconfig.yamlの値を使う）
+    // Host key verification settings (unset fields fall back to config.yaml)
+    pub host_keys: Option<HostKeySettings>,
+
+    // 転送速度の上限（固定値、または時間帯ごとの値。config.yamlの上限も適用される）
+    // Transfer rate limit (a fixed rate or one per time of day; the cap in config.yaml also applies)
+    pub bandwidth_limit: Option<BandwidthLimit>,
+
+    // 転送できるファイルサイズの上限（MB、0で無制限）。config.yamlの値より優先する
+    // Maximum file size that may be transferred (MB, 0 for unlimited). Takes precedence over config.yaml
+    pub max_file_size_mb: Option<u64>,
 }
 
 
@@ -61,7 +97,13 @@ impl Default for TransferProfile {
                 trigger: Trigger {
                     kind: TriggerType::Manual,
                     schedule: None,
+                    missed_runs: None,
+                    overlap: None,
+                    debounce_ms: None,
                 },
+                filter: None,
+                on_success: None,
+                stability: None,
             },
             destination: Destination {
                 kind: DestinationType::Local,
@@ -76,6 +118,15 @@ impl Default for TransferProfile {
             pre_transfer_command: None,
             post_transfer_command: None,
             on_error_command: None,
+            recursive: None,
+            resume: None,
+            verify: None,
+            atomic: None,
+            retry: None,
+            timeouts: None,
+            host_keys: None,
+            bandwidth_limit: None,
+            max_file_size_mb: None,
         }
     }
 }
@@ -92,13 +143,32 @@ pub struct Source {
     #[serde(rename = "type")]
     pub kind: SourceType,
 
-    #[validate(custom(function = "validate_cross_platform_path"))]
+    #[validate(custom(function = "validate_source_path"))]
     pub path: String,
 
     pub host: Option<String>,
     pub port: Option<u16>,
     pub authentication: Option<Authentication>,
     pub trigger: Trigger,
+
+    // 転送対象ファイルの選別条件（include/exclude）
+    // Selection rules for the files to transfer (include/exclude)
+    pub filter: Option<FileFilter>,
+
+    // 転送成功後の転送元ファイルの扱い（keep / delete / moveTo / renameSuffix）
+    // What to do with the source files after a successful transfer (keep / delete / moveTo / renameSuffix)
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub on_success: Option<SourceDisposition>,
+
+    // 書き込み中のファイルを転送しないための条件（サイズと更新日時の安定、添付のトリガーファイル）
+    // Conditions that keep files still being written from being picked up (stable size and mtime, a companion trigger file)
+    pub stability: Option<Stability>,
+}
+
+// sshConfig認証ではポートを省略でき、ssh_configの `Port`（なければ22）を使う
+// With sshConfig authentication the port may be omitted; ssh_config's `Port` (or 22) is used
+fn uses_ssh_config(authentication: Option<&Authentication>) -> bool {
+    authentication.is_some_and(|auth| auth.method == AuthenticationMethod::SshConfig)
 }
 
 impl Source {
@@ -113,7 +183,7 @@ impl Source {
                         "SFTP source requires 'host'".to_string(),
                     ));
                 }
-                if self.port.is_none() {
+                if self.port.is_none() && !uses_ssh_config(self.authentication.as_ref()) {
                     return Err(AppError::Validation(
                         "SFTP source requires 'port'".to_string(),
                     ));
@@ -132,7 +202,7 @@ impl Source {
                         "SCP source requires 'host'".to_string(),
                     ));
                 }
-                if self.port.is_none() {
+                if self.port.is_none() && !uses_ssh_config(self.authentication.as_ref()) {
                     return Err(AppError::Validation(
                         "SCP source requires 'port'".to_string(),
                     ));
@@ -147,10 +217,21 @@ impl Source {
             }
         }
 
+        if let Some(stability) = &self.stability {
+            stability.validate()?;
+        }
+
         match self.trigger.kind {
             TriggerType::Manual => {
                 println!("manual")
             }
+            TriggerType::Watch => {
+                if self.kind != SourceType::Local {
+                    return Err(AppError::Validation(
+                        "Trigger type 'watch' requires a local source".to_string(),
+                    ));
+                }
+            }
             TriggerType::Schedule => {
                 if let Some(s) = &self.trigger.schedule {
                     s.parse::<cron::Schedule>()
@@ -166,12 +247,66 @@ impl Source {
             }
         }
 
+        // パターンがコンパイルできることを確認
+        // Make sure the selection patterns compile
+        FileSelection::from_source(self)?;
+
+        match &self.on_success {
+            Some(SourceDisposition::MoveTo(dir)) if dir.trim().is_empty() => {
+                return Err(AppError::Validation(
+                    "onSuccess 'moveTo' requires a directory".to_string(),
+                ));
+            }
+            Some(SourceDisposition::RenameSuffix(suffix)) if suffix.trim().is_empty() => {
+                return Err(AppError::Validation(
+                    "onSuccess 'renameSuffix' requires a suffix".to_string(),
+                ));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.max_attempts == Some(0) {
+            return Err(AppError::Validation(
+                "retry 'maxAttempts' must be at least 1".to_string(),
+            ));
+        }
+        if let Some(factor) = self.backoff_factor
+            && !(factor.is_finite() && factor >= 1.0)
+        {
+            return Err(AppError::Validation(
+                "retry 'backoffFactor' must be 1.0 or greater".to_string(),
+            ));
+        }
+        if let Some(jitter) = self.jitter
+            && !(0.0..=1.0).contains(&jitter)
+        {
+            return Err(AppError::Validation(
+                "retry 'jitter' must be between 0.0 and 1.0".to_string(),
+            ));
+        }
         Ok(())
     }
 }
 
 impl Authentication {
     pub fn validate(&self) -> Result<(), AppError> {
+        for jump in self.jump_hosts.as_deref().unwrap_or_default() {
+            if jump.host.trim().is_empty() {
+                return Err(AppError::Validation(
+                    "Jump host requires 'host'".to_string(),
+                ));
+            }
+            if let Some(auth) = &jump.authentication {
+                auth.validate()?;
+            }
+        }
+
         match self.method {
             AuthenticationMethod::Password => {
                 if self.password_ref.is_none() {
@@ -186,6 +321,13 @@ impl Authentication {
                         "Private key or Env key requires 'privateKeyRef'".to_string(),
                     ));
                 }
+                // 暗号化された鍵にはパスフレーズが必要
+                // An encrypted key needs a passphrase
+                if self.passphrase_ref.is_none()
+                    && let Ok(key_path) = get_private_key_path(self)
+                {
+                    ensure_passphrase_available(Path::new(&key_path), None)?;
+                }
             } // Manualなど、他の認証方法があればここに追加
             AuthenticationMethod::SshConfig => {
                 if self.ssh_config_alias.is_none() {
@@ -193,6 +335,35 @@ impl Authentication {
                         "SSH config authentication requires 'sshConfigAlias'".to_string(),
                     ));
                 }
+                if let Some(file) = &self.ssh_config_file
+                    && !Path::new(shellexpand::tilde(file).as_ref()).is_file()
+                {
+                    return Err(AppError::Validation(format!(
+                        "SSH config file '{}' does not exist",
+                        file
+                    )));
+                }
+            }
+            AuthenticationMethod::Agent => {
+                // ssh-agentの鍵を使うため、追加の設定は不要
+                // Keys come from ssh-agent, so nothing else is required
+            }
+            AuthenticationMethod::KeyboardInteractive => {
+                let prompts = self.prompts.as_deref().unwrap_or_default();
+                if prompts.is_empty() && self.password_ref.is_none() {
+                    return Err(AppError::Validation(
+                        "Keyboard-interactive authentication requires 'prompts' or 'passwordRef'"
+                            .to_string(),
+                    ));
+                }
+                for prompt in prompts {
+                    regex::Regex::new(&prompt.pattern).map_err(|e| {
+                        AppError::Validation(format!(
+                            "Invalid prompt pattern '{}': {}",
+                            prompt.pattern, e
+                        ))
+                    })?;
+                }
             }
         }
         Ok(())
@@ -229,6 +400,48 @@ impl FromStr for SourceType {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileFilter {
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub syntax: Option<PatternSyntax>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stability {
+    // サイズと更新日時が変わらないまま経過する必要がある秒数（未指定か0の場合は確認しない）
+    // Seconds the size and mtime must stay unchanged (not checked when omitted or 0)
+    pub stable_secs: Option<u64>,
+    // 安定するまで待つ最大秒数（未指定の場合は DEFAULT_STABILITY_MAX_WAIT_SECS）
+    // Maximum seconds to wait for files to settle (DEFAULT_STABILITY_MAX_WAIT_SECS when omitted)
+    pub max_wait_secs: Option<u64>,
+    // 転送の条件とするトリガーファイルの接尾辞（例: ".ok" の場合、report.csv には report.csv.ok が必要）
+    // Suffix of the companion trigger file required for transfer (with ".ok", report.csv needs report.csv.ok)
+    pub trigger_file_suffix: Option<String>,
+    // 転送成功後にトリガーファイルを削除するかどうか（未指定の場合は残す）
+    // Whether to delete the trigger file after a successful transfer (kept when omitted)
+    pub delete_trigger_file: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SourceDisposition {
+    Keep,
+    Delete,
+    MoveTo(String),
+    RenameSuffix(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternSyntax {
+    #[default]
+    Glob,
+    Regex,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Authentication {
@@ -236,7 +449,46 @@ pub struct Authentication {
     pub username: String,
     pub password_ref: Option<String>,
     pub private_key_ref: Option<String>,
+    // 暗号化された秘密鍵のパスフレーズの参照先（環境変数名、または `file:<パス>`）
+    // Where to read the passphrase of an encrypted private key from (an env var name, or `file:<path>`)
+    pub passphrase_ref: Option<String>,
     pub ssh_config_alias: Option<String>,
+    // sshConfigで読み込む設定ファイル（未指定の場合は ~/.ssh/config）
+    // ssh_config file read for sshConfig authentication (defaults to ~/.ssh/config)
+    pub ssh_config_file: Option<String>,
+
+    // keyboard-interactive認証で、プロンプトに一致する正規表現と応答する秘密情報の参照先
+    // For keyboard-interactive authentication: prompt regexes and the secret refs that answer them
+    pub prompts: Option<Vec<PromptResponse>>,
+
+    // known_hostsを使わずに照合するホスト鍵のSHA256フィンガープリント（例: SHA256:abc...）
+    // SHA256 fingerprint of the host key to pin instead of using known_hosts (e.g. SHA256:abc...)
+    pub host_key_fingerprint: Option<String>,
+
+    // 経由する踏み台ホスト（先頭から順に接続する。未指定でsshConfigの場合はProxyJumpを使う）
+    // Jump hosts to tunnel through, in connection order (ProxyJump is used for sshConfig when omitted)
+    pub jump_hosts: Option<Vec<JumpHost>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JumpHost {
+    pub host: String,
+    pub port: Option<u16>,
+    // 踏み台ホストの認証情報（未指定の場合は接続先の認証情報を使う）
+    // Authentication for the jump host (the target's authentication is reused when omitted)
+    pub authentication: Option<Authentication>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptResponse {
+    // サーバーのプロンプト文字列に一致させる正規表現（例: "(?i)verification code"）
+    // Regex matched against the server's prompt text (e.g. "(?i)verification code")
+    pub pattern: String,
+    // 応答する秘密情報の参照先（環境変数名、または `file:<パス>`）
+    // Where to read the answer from (an env var name, or `file:<path>`)
+    pub secret_ref: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -246,6 +498,8 @@ pub enum AuthenticationMethod {
     PrivateKey,
     EnvKey,
     SshConfig,
+    Agent,
+    KeyboardInteractive,
 }
 
 impl ToString for AuthenticationMethod {
@@ -255,6 +509,8 @@ impl ToString for AuthenticationMethod {
             AuthenticationMethod::PrivateKey => "private_key".into(),
             AuthenticationMethod::EnvKey => "env_key".into(),
             AuthenticationMethod::SshConfig => "ssh_config".into(),
+            AuthenticationMethod::Agent => "agent".into(),
+            AuthenticationMethod::KeyboardInteractive => "keyboard_interactive".into(),
         }
     } 
 }
@@ -268,6 +524,8 @@ impl FromStr for AuthenticationMethod {
             "private_key" => Ok(AuthenticationMethod::PrivateKey),
             "env_key" => Ok(AuthenticationMethod::EnvKey),
             "ssh_config" => Ok(AuthenticationMethod::SshConfig),
+            "agent" => Ok(AuthenticationMethod::Agent),
+            "keyboard_interactive" => Ok(AuthenticationMethod::KeyboardInteractive),
             other => Err(format!("'{}' is not allowed", other).into())
         }
     }
@@ -279,28 +537,101 @@ pub struct Trigger {
     #[serde(rename = "type")] // YAMLの'type'キーをRustの'kind'フィールドにマッピング
     pub kind: TriggerType,
     pub schedule: Option<String>,
+
+    // デーモンが停止していた間に過ぎた実行時刻の扱い（skip / run-once / run-all、未指定の場合は skip）
+    // What to do with run times that passed while the daemon was down (skip / run-once / run-all, skip when omitted)
+    pub missed_runs: Option<MissedRunPolicy>,
+
+    // 前回の実行が終わっていない場合の扱い（skip / queue / allow、未指定の場合は skip）
+    // What to do when the previous run is still in progress (skip / queue / allow, skip when omitted)
+    pub overlap: Option<OverlapPolicy>,
+
+    // watch: 最後のファイルイベントから実行までに待つ時間（ミリ秒、未指定の場合は DEFAULT_WATCH_DEBOUNCE_MS）
+    // watch: how long to wait after the last file event before running (milliseconds, DEFAULT_WATCH_DEBOUNCE_MS when omitted)
+    pub debounce_ms: Option<u64>,
 }
 
 impl Trigger {
     pub fn validate(&self) -> Result<(), AppError> {
-        // Result<(), anyhow::Error> を返す
-        if self.kind == TriggerType::Schedule {
-            // schedule が None の場合は AppError::MissingSchedule を返す
-            let schedule_expression = self
-                .schedule
-                .as_ref()
-                .ok_or_else(|| AppError::MissingSchedule)?; // `?` で早期リターン
-
-            // Cron式のパースを試みる
-            // パースに失敗した場合は AppError::InvalidCronSchedule を返す
-            cron::Schedule::from_str(schedule_expression).map_err(|e| {
-                AppError::InvalidCronSchedule {
-                    expression: schedule_expression.clone(), // パースできなかった文字列
-                    source: e,                               // cron::error::Error
-                }
-            })?;
+        self.cron_schedule().map(|_| ())
+    }
+
+    /// watch トリガーのデバウンス時間
+    /// Debounce time of a watch trigger
+    pub fn debounce(&self) -> Duration {
+        Duration::from_millis(self.debounce_ms.unwrap_or(DEFAULT_WATCH_DEBOUNCE_MS))
+    }
+
+    /// スケジュール起動の場合はCron式を解析して返す（手動起動の場合はNone）
+    /// Parse and return the cron expression for scheduled triggers (None for manual ones)
+    pub fn cron_schedule(&self) -> Result<Option<cron::Schedule>, AppError> {
+        if self.kind != TriggerType::Schedule {
+            return Ok(None);
+        }
+        // schedule が None の場合は AppError::MissingSchedule を返す
+        let schedule_expression = self
+            .schedule
+            .as_ref()
+            .ok_or_else(|| AppError::MissingSchedule)?; // `?` で早期リターン
+
+        // Cron式のパースを試みる
+        // パースに失敗した場合は AppError::InvalidCronSchedule を返す
+        cron::Schedule::from_str(schedule_expression)
+            .map(Some)
+            .map_err(|e| AppError::InvalidCronSchedule {
+                expression: schedule_expression.clone(), // パースできなかった文字列
+                source: e,                               // cron::error::Error
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MissedRunPolicy {
+    // 過ぎた実行時刻は実行しない
+    // Do not run for the run times that passed
+    #[default]
+    Skip,
+    // 過ぎた実行時刻がいくつあっても1回だけ実行する
+    // Run once, however many run times passed
+    RunOnce,
+    // 過ぎた実行時刻ごとに1回ずつ、順番に実行する
+    // Run once per run time that passed, one after another
+    RunAll,
+}
+
+impl fmt::Display for MissedRunPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MissedRunPolicy::Skip => write!(f, "skip"),
+            MissedRunPolicy::RunOnce => write!(f, "run-once"),
+            MissedRunPolicy::RunAll => write!(f, "run-all"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverlapPolicy {
+    // 前回の実行中に迎えた実行時刻は実行しない
+    // Do not run when the previous run is still in progress
+    #[default]
+    Skip,
+    // 前回の実行が終わってから実行する
+    // Run after the previous run has finished
+    Queue,
+    // 前回の実行と並行して実行する
+    // Run alongside the previous run
+    Allow,
+}
+
+impl fmt::Display for OverlapPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverlapPolicy::Skip => write!(f, "skip"),
+            OverlapPolicy::Queue => write!(f, "queue"),
+            OverlapPolicy::Allow => write!(f, "allow"),
         }
-        Ok(())
     }
 }
 
@@ -309,6 +640,9 @@ impl Trigger {
 pub enum TriggerType {
     Manual,
     Schedule,
+    // デーモンが転送元のローカルディレクトリを監視し、ファイルが置かれたら実行する
+    // The daemon watches the local source directory and runs when a file is dropped there
+    Watch,
 }
 
 impl ToString for TriggerType {
@@ -316,6 +650,7 @@ impl ToString for TriggerType {
         match self {
             TriggerType::Manual => "manual".into(),
             TriggerType::Schedule => "schedule".into(),
+            TriggerType::Watch => "watch".into(),
         }
     }
 }
@@ -326,6 +661,7 @@ impl FromStr for TriggerType {
         match s.to_lowercase().as_str() {
             "sftp" => Ok(TriggerType::Manual),
             "scp" => Ok(TriggerType::Schedule),
+            "watch" => Ok(TriggerType::Watch),
             other => Err(format!("'{}' is not allowed", other).into())
         }
     }
@@ -357,7 +693,7 @@ impl Destination {
                         "SFTP destination requires 'host'".to_string(),
                     ));
                 }
-                if self.port.is_none() {
+                if self.port.is_none() && !uses_ssh_config(self.authentication.as_ref()) {
                     return Err(AppError::Validation(
                         "SFTP destination requires 'port'".to_string(),
                     ));
@@ -376,7 +712,7 @@ impl Destination {
                         "SCP destination requires 'host'".to_string(),
                     ));
                 }
-                if self.port.is_none() {
+                if self.port.is_none() && !uses_ssh_config(self.authentication.as_ref()) {
                     return Err(AppError::Validation(
                         "SCP destination requires 'port'".to_string(),
                     ));
@@ -426,6 +762,232 @@ impl FromStr for DestinationType {
 }
 
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AtomicDelivery {
+    // 一時ファイル名の接頭辞・接尾辞（どちらも未指定の場合は接尾辞 `.part`）
+    // Prefix/suffix of the temporary file name (suffix `.part` when neither is set)
+    pub temp_prefix: Option<String>,
+    pub temp_suffix: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    // 最大試行回数（初回を含む）
+    // Maximum number of attempts, including the first one
+    pub max_attempts: Option<u32>,
+
+    // 初回の再試行までの待ち時間（ミリ秒）と、試行ごとの倍率・上限
+    // Delay before the first retry (milliseconds), its growth factor per attempt and its cap
+    pub initial_delay_ms: Option<u64>,
+    pub backoff_factor: Option<f64>,
+    pub max_delay_ms: Option<u64>,
+
+    // 待ち時間に加えるゆらぎの割合（0.0〜1.0）
+    // Fraction of random variation applied to each delay (0.0 to 1.0)
+    pub jitter: Option<f64>,
+
+    // 再試行の対象とするエラー分類（未指定の場合は connect / timeout / io）
+    // Error classes that are retried (connect / timeout / io when omitted)
+    pub retry_on: Option<Vec<ErrorClass>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorClass {
+    Connect,
+    Timeout,
+    Auth,
+    Io,
+    Verify,
+    Validation,
+    SizeLimit,
+    Other,
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorClass::Connect => "connect",
+            ErrorClass::Timeout => "timeout",
+            ErrorClass::Auth => "auth",
+            ErrorClass::Io => "io",
+            ErrorClass::Verify => "verify",
+            ErrorClass::Validation => "validation",
+            ErrorClass::SizeLimit => "sizeLimit",
+            ErrorClass::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyMethod {
+    Sha256,
+    Md5,
+    Size,
+}
+
+impl fmt::Display for VerifyMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyMethod::Sha256 => write!(f, "sha256"),
+            VerifyMethod::Md5 => write!(f, "md5"),
+            VerifyMethod::Size => write!(f, "size"),
+        }
+    }
+}
+
+impl FromStr for VerifyMethod {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha256" => Ok(VerifyMethod::Sha256),
+            "md5" => Ok(VerifyMethod::Md5),
+            "size" => Ok(VerifyMethod::Size),
+            other => Err(format!("'{}' is not allowed", other)),
+        }
+    }
+}
+
+/// 転送速度（`10MiB/s` / `500KB/s` / `unlimited` の形式で指定する）
+/// A transfer rate (written as `10MiB/s`, `500KB/s` or `unlimited`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Bandwidth {
+    Unlimited,
+    BytesPerSec(u64),
+}
+
+impl Bandwidth {
+    pub fn bytes_per_sec(&self) -> Option<u64> {
+        match self {
+            Bandwidth::Unlimited => None,
+            Bandwidth::BytesPerSec(rate) => Some(*rate),
+        }
+    }
+}
+
+impl fmt::Display for Bandwidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [(u64, &str); 3] = [(1024 * 1024 * 1024, "GiB"), (1024 * 1024, "MiB"), (1024, "KiB")];
+        match self {
+            Bandwidth::Unlimited => write!(f, "unlimited"),
+            Bandwidth::BytesPerSec(rate) => {
+                match UNITS.iter().find(|(size, _)| *rate >= *size && rate % size == 0) {
+                    Some((size, unit)) => write!(f, "{}{}/s", rate / size, unit),
+                    None => write!(f, "{}B/s", rate),
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Bandwidth {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let value = s.trim();
+        if value.eq_ignore_ascii_case("unlimited") {
+            return Ok(Bandwidth::Unlimited);
+        }
+        let rate = value.strip_suffix("/s").unwrap_or(value).trim();
+        let split = rate
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rate.len());
+        let (number, unit) = rate.split_at(split);
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("'{}' is not a valid bandwidth (e.g. 10MiB/s)", s))?;
+        let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kb" => 1000,
+            "kib" => 1024,
+            "m" | "mb" => 1000 * 1000,
+            "mib" => 1024 * 1024,
+            "g" | "gb" => 1000 * 1000 * 1000,
+            "gib" => 1024 * 1024 * 1024,
+            other => return Err(format!("'{}' is not a valid bandwidth unit", other)),
+        };
+        let bytes = (number * multiplier as f64).round();
+        if bytes < 1.0 {
+            return Err(format!("'{}' must be at least 1B/s", s));
+        }
+        Ok(Bandwidth::BytesPerSec(bytes as u64))
+    }
+}
+
+impl TryFrom<String> for Bandwidth {
+    type Error = String;
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Bandwidth> for String {
+    fn from(value: Bandwidth) -> Self {
+        value.to_string()
+    }
+}
+
+/// 転送速度の上限。固定値か、時間帯ごとに切り替える設定のどちらかで指定する。
+/// A transfer rate limit, either fixed or switched by time of day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BandwidthLimit {
+    Fixed(Bandwidth),
+    Scheduled(BandwidthSchedule),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthSchedule {
+    // どの時間帯にも当てはまらない場合の上限（未指定の場合は無制限）
+    // Limit outside every window (unlimited when omitted)
+    pub default: Option<Bandwidth>,
+    // 上から順に評価し、最初に当てはまった時間帯の上限を使う
+    // Evaluated top to bottom; the first matching window wins
+    pub windows: Vec<BandwidthWindow>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthWindow {
+    // 開始時刻（含む）と終了時刻（含まない）、`22:00`〜`06:00` のように日付をまたいでもよい
+    // Start (inclusive) and end (exclusive) time; may wrap past midnight like `22:00` to `06:00`
+    pub from: chrono::NaiveTime,
+    pub to: chrono::NaiveTime,
+    pub limit: Bandwidth,
+}
+
+impl BandwidthWindow {
+    pub fn contains(&self, time: chrono::NaiveTime) -> bool {
+        if self.from <= self.to {
+            self.from <= time && time < self.to
+        } else {
+            time >= self.from || time < self.to
+        }
+    }
+}
+
+impl BandwidthLimit {
+    /// 指定した時刻の上限（バイト/秒、Noneは無制限）
+    /// The limit in effect at the given time of day (bytes per second, None for unlimited)
+    pub fn bytes_per_sec_at(&self, time: chrono::NaiveTime) -> Option<u64> {
+        match self {
+            BandwidthLimit::Fixed(bandwidth) => bandwidth.bytes_per_sec(),
+            BandwidthLimit::Scheduled(schedule) => schedule
+                .windows
+                .iter()
+                .find(|window| window.contains(time))
+                .map(|window| window.limit)
+                .or(schedule.default)
+                .and_then(|bandwidth| bandwidth.bytes_per_sec()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TransferProtocol {
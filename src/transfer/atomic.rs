@@ -0,0 +1,153 @@
+use crate::{exec_remote, shell_quote, AtomicDelivery};
+use anyhow::{Context, Result};
+use log::{debug, info};
+use ssh2::{ErrorCode, RenameFlags, Session, Sftp};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// 一時ファイル名の既定のサフィックス
+/// Default suffix for temporary file names
+pub const DEFAULT_TEMP_SUFFIX: &str = ".part";
+
+impl AtomicDelivery {
+    fn prefix(&self) -> &str {
+        self.temp_prefix.as_deref().unwrap_or_default()
+    }
+
+    fn suffix(&self) -> &str {
+        match (&self.temp_prefix, &self.temp_suffix) {
+            (_, Some(suffix)) => suffix,
+            (Some(_), None) => "",
+            (None, None) => DEFAULT_TEMP_SUFFIX,
+        }
+    }
+
+    /// 転送先と同じディレクトリ内の一時ファイルパスを返す（例: `report.csv.part`）
+    /// Temporary path next to the destination (e.g. `report.csv.part`)
+    pub fn temp_path(&self, dst: &Path) -> PathBuf {
+        let name = dst
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        dst.with_file_name(format!("{}{}{}", self.prefix(), name, self.suffix()))
+    }
+
+    /// ローカルの転送先ディレクトリに一時ファイルを作成する
+    /// Create a temporary file in the local destination directory
+    pub fn local_temp_file(&self, dst: &Path) -> Result<NamedTempFile> {
+        let dir = match dst.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let name = dst
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        tempfile::Builder::new()
+            .prefix(&format!("{}{}.", self.prefix(), name))
+            .suffix(self.suffix())
+            .tempfile_in(dir)
+            .with_context(|| {
+                format!(
+                    "Failed to create temporary file in local directory: '{}'",
+                    dir.display()
+                )
+            })
+    }
+}
+
+/// 一時ファイルを最終的なファイル名へリネームする。
+/// SFTPが利用できない場合は同じセッション上で `mv -f` を実行する。
+/// Rename the temporary file into its final name.
+/// Falls back to running `mv -f` over the same session when SFTP is not available.
+pub fn commit_remote_file(
+    session: &Session,
+    sftp: Option<&Sftp>,
+    temp: &Path,
+    dst: &Path,
+) -> Result<()> {
+    match sftp {
+        Some(sftp) => {
+            let flags = RenameFlags::OVERWRITE | RenameFlags::ATOMIC | RenameFlags::NATIVE;
+            if let Err(e) = sftp.rename(temp, dst, Some(flags)) {
+                // SFTP v3のサーバーは既存ファイルへの上書きリネームを拒否することがある。
+                // それ以外の失敗では既存のファイルを残すため、元のエラーを返す。
+                // SFTP v3 servers may refuse to rename over an existing file.
+                // Any other failure returns the original error so the existing file is kept.
+                if !refused_overwrite(sftp, &e, temp, dst) {
+                    return Err(e).with_context(|| {
+                        format!(
+                            "Failed to rename remote file '{}' to '{}'",
+                            temp.display(),
+                            dst.display()
+                        )
+                    });
+                }
+                debug!(
+                    "Rename of '{}' to '{}' failed ({}). Removing the existing file and retrying.",
+                    temp.display(),
+                    dst.display(),
+                    e
+                );
+                sftp.unlink(dst).with_context(|| {
+                    format!("Failed to remove existing remote file '{}'", dst.display())
+                })?;
+                sftp.rename(temp, dst, None).with_context(|| {
+                    format!(
+                        "Failed to rename remote file '{}' to '{}'",
+                        temp.display(),
+                        dst.display()
+                    )
+                })?;
+            }
+        }
+        None => {
+            exec_remote(
+                session,
+                &format!("mv -f {} {}", shell_quote(temp), shell_quote(dst)),
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to rename remote file '{}' to '{}'",
+                    temp.display(),
+                    dst.display()
+                )
+            })?;
+        }
+    }
+    info!(
+        "Renamed temporary file '{}' to '{}'",
+        temp.display(),
+        dst.display()
+    );
+    Ok(())
+}
+
+// リネームの失敗が、既存のファイルへの上書きを拒否されたことによるものかどうか
+// Whether a failed rename was the server refusing to overwrite an existing file
+fn refused_overwrite(sftp: &Sftp, error: &ssh2::Error, temp: &Path, dst: &Path) -> bool {
+    // SSH_FX_FAILURE(4) または SSH_FX_FILE_ALREADY_EXISTS(11) のみ対象とする
+    // Only SSH_FX_FAILURE (4) or SSH_FX_FILE_ALREADY_EXISTS (11) qualify
+    matches!(error.code(), ErrorCode::SFTP(4 | 11))
+        && sftp.stat(temp).is_ok()
+        && sftp.stat(dst).is_ok()
+}
+
+/// ローカルの一時ファイルを最終的なファイル名へ確定する
+/// Persist a local temporary file under its final name
+pub fn commit_local_file(temp: NamedTempFile, dst: &Path) -> Result<()> {
+    let temp_path = temp.path().to_path_buf();
+    temp.persist(dst).with_context(|| {
+        format!(
+            "Failed to move temporary file '{}' to '{}'",
+            temp_path.display(),
+            dst.display()
+        )
+    })?;
+    info!(
+        "Renamed temporary file '{}' to '{}'",
+        temp_path.display(),
+        dst.display()
+    );
+    Ok(())
+}
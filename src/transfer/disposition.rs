@@ -0,0 +1,228 @@
+use crate::{
+    ensure_remote_dir, exec_remote, shell_quote, SourceDisposition, SourceType, TransferProfile,
+    TransferredFile,
+};
+use anyhow::{Context, Result};
+use log::{debug, info};
+use ssh2::{RenameFlags, Session, Sftp};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// 転送成功後に、転送元ファイルを `source.onSuccess` に従って処理し、
+/// `stability.deleteTriggerFile` が有効ならトリガーファイルを削除する。
+/// リモートの転送元は、転送に使った転送元ホストへのセッション `session` 上で
+/// SFTP（利用できなければシェルコマンド）を使って処理する。
+/// Apply `source.onSuccess` to the source files after a successful transfer,
+/// and delete the trigger files when `stability.deleteTriggerFile` is set.
+/// Remote sources are handled over `session`, the session to the source host used for the transfer,
+/// using SFTP (or shell commands when SFTP is not available).
+pub fn dispose_sources(
+    profile: &TransferProfile,
+    files: &[TransferredFile],
+    session: Option<&Session>,
+) -> Result<()> {
+    let disposition = profile
+        .source
+        .on_success
+        .as_ref()
+        .filter(|disposition| **disposition != SourceDisposition::Keep);
+    let trigger_files: Vec<PathBuf> = profile
+        .source
+        .stability
+        .as_ref()
+        .filter(|stability| stability.deletes_trigger_file())
+        .map(|stability| {
+            files
+                .iter()
+                .filter_map(|file| stability.trigger_file(&file.source))
+                .collect()
+        })
+        .unwrap_or_default();
+    if files.is_empty() || (disposition.is_none() && trigger_files.is_empty()) {
+        return Ok(());
+    }
+
+    if let Some(disposition) = disposition {
+        info!(
+            "Applying source disposition {:?} to {} file(s) for profile '{}'.",
+            disposition,
+            files.len(),
+            profile.profile_id
+        );
+    }
+
+    match profile.source.kind {
+        SourceType::Local => {
+            for file in files {
+                if let Some(disposition) = disposition {
+                    dispose_local(disposition, file)?;
+                }
+            }
+            for trigger in &trigger_files {
+                delete_local(trigger)?;
+            }
+        }
+        SourceType::Sftp | SourceType::Scp => {
+            let session = session.context(
+                "Disposing of remote source files requires the session used for the transfer",
+            )?;
+            let sftp = session.sftp().ok();
+            for file in files {
+                if let Some(disposition) = disposition {
+                    dispose_remote(session, sftp.as_ref(), disposition, file)?;
+                }
+            }
+            for trigger in &trigger_files {
+                delete_remote(session, sftp.as_ref(), trigger)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn target_path(disposition: &SourceDisposition, file: &TransferredFile) -> Option<PathBuf> {
+    match disposition {
+        SourceDisposition::MoveTo(dir) => Some(Path::new(dir).join(&file.relative)),
+        SourceDisposition::RenameSuffix(suffix) => {
+            let mut renamed = file.source.clone().into_os_string();
+            renamed.push(suffix);
+            Some(PathBuf::from(renamed))
+        }
+        SourceDisposition::Keep | SourceDisposition::Delete => None,
+    }
+}
+
+fn dispose_local(disposition: &SourceDisposition, file: &TransferredFile) -> Result<()> {
+    let source = &file.source;
+    match target_path(disposition, file) {
+        None => delete_local(source)?,
+        Some(target) => {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).with_context(|| {
+                    format!("Failed to create local directory: '{}'", parent.display())
+                })?;
+            }
+            if let Err(e) = fs::rename(source, &target) {
+                // 別のファイルシステムへの移動はrenameできないため、コピーしてから削除する
+                // Moving across filesystems cannot be done with rename, so copy and then remove
+                debug!(
+                    "Rename of '{}' failed ({}). Falling back to copy and delete.",
+                    source.display(),
+                    e
+                );
+                fs::copy(source, &target)
+                    .and_then(|_| fs::remove_file(source))
+                    .with_context(|| {
+                        format!(
+                            "Failed to move local source file '{}' to '{}'",
+                            source.display(),
+                            target.display()
+                        )
+                    })?;
+            }
+            info!(
+                "Moved local source file '{}' to '{}'",
+                source.display(),
+                target.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn dispose_remote(
+    session: &Session,
+    sftp: Option<&Sftp>,
+    disposition: &SourceDisposition,
+    file: &TransferredFile,
+) -> Result<()> {
+    let source = &file.source;
+    let Some(target) = target_path(disposition, file) else {
+        return delete_remote(session, sftp, source);
+    };
+    match sftp {
+        Some(sftp) => {
+            if let Some(parent) = target.parent() {
+                ensure_remote_dir_all(sftp, parent)?;
+            }
+            sftp.rename(source, &target, Some(RenameFlags::OVERWRITE | RenameFlags::NATIVE))
+                .with_context(|| {
+                    format!(
+                        "Failed to move remote source file '{}' to '{}'",
+                        source.display(),
+                        target.display()
+                    )
+                })?;
+        }
+        None => {
+            let parent = target.parent().unwrap_or_else(|| Path::new("."));
+            exec_remote(
+                session,
+                &format!(
+                    "mkdir -p {} && mv -f {} {}",
+                    shell_quote(parent),
+                    shell_quote(source),
+                    shell_quote(&target)
+                ),
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to move remote source file '{}' to '{}'",
+                    source.display(),
+                    target.display()
+                )
+            })?;
+        }
+    }
+
+    info!(
+        "Moved remote source file '{}' to '{}'",
+        source.display(),
+        target.display()
+    );
+    Ok(())
+}
+
+fn delete_local(path: &Path) -> Result<()> {
+    fs::remove_file(path).with_context(|| {
+        format!("Failed to delete local source file: '{}'", path.display())
+    })?;
+    info!("Deleted local source file '{}'", path.display());
+    Ok(())
+}
+
+fn delete_remote(session: &Session, sftp: Option<&Sftp>, path: &Path) -> Result<()> {
+    match sftp {
+        Some(sftp) => {
+            sftp.unlink(path).with_context(|| {
+                format!("Failed to delete remote source file: '{}'", path.display())
+            })?;
+        }
+        None => {
+            exec_remote(session, &format!("rm -f {}", shell_quote(path))).with_context(|| {
+                format!("Failed to delete remote source file: '{}'", path.display())
+            })?;
+        }
+    }
+    info!("Deleted remote source file '{}'", path.display());
+    Ok(())
+}
+
+// 親ディレクトリも含めてリモートディレクトリを作成する
+// Create a remote directory including its missing parents
+fn ensure_remote_dir_all(sftp: &Sftp, dir: &Path) -> Result<()> {
+    let mut current = PathBuf::new();
+    for component in dir.components() {
+        current.push(component);
+        if current.parent().is_none() {
+            // ルート（`/`）はスキップ
+            // Skip the root (`/`)
+            continue;
+        }
+        ensure_remote_dir(sftp, &current)?;
+    }
+    Ok(())
+}
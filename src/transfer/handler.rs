@@ -1,28 +1,85 @@
 use crate::{
-    execute_command, AppError, ProtocolType, ScpHandler, SftpHandler, SourceType, TransferProfile,
-    TransferProtocolHandler,
+    dispose_sources, execute_command, AppError, ProfileLock, ProtocolType, ScpHandler, SftpHandler, SourceType,
+    TransferContext, TransferOutcome, TransferProfile, TransferProtocolHandler, TransferReport,
+    surface_timeout, with_retry,
 };
-use anyhow::Result;
-use log::{error, info};
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use log::{error, info, warn};
 
-pub async fn process_transfer_profile(profile: TransferProfile) -> Result<()> {
+/// プロファイルの転送を実行し、結果を `TransferReport` として返す。
+/// 失敗した場合も、エラーのコンテキストとしてレポートを取り出せる（`downcast_ref::<TransferReport>()`）。
+/// Run a profile's transfer and return the result as a `TransferReport`.
+/// On failure the report is still available as error context (`downcast_ref::<TransferReport>()`).
+pub async fn process_transfer_profile(profile: TransferProfile) -> Result<TransferReport> {
+    process_transfer_profile_with_context(profile, TransferContext::default()).await
+}
+
+/// `context` のキャンセル用トークンで中断できるようにプロファイルの転送を実行する
+/// Run a profile's transfer so that it can be stopped through the cancellation token in `context`
+pub async fn process_transfer_profile_with_context(
+    profile: TransferProfile,
+    context: TransferContext,
+) -> Result<TransferReport> {
+    let mut report = TransferReport::start(&profile.profile_id);
+    // フックも含めた実行全体の間、プロファイルのロックを保持する
+    // Hold the profile's lock for the whole run, hooks included
+    let result = async {
+        let _lock = if context.options.profile_lock {
+            Some(ProfileLock::acquire(&profile.profile_id, &context).await?)
+        } else {
+            None
+        };
+        run_profile(Arc::new(profile), &context, &mut report).await
+    }
+    .await;
+    report.finish(result.as_ref().err());
+    match result {
+        Ok(()) => Ok(report),
+        Err(e) => Err(e.context(report)),
+    }
+}
+
+async fn run_profile(
+    profile: Arc<TransferProfile>,
+    context: &TransferContext,
+    report: &mut TransferReport,
+) -> Result<()> {
     // Validation
     profile.source.validate()?;
     profile.destination.validate()?;
+    if let Some(retry) = &profile.retry {
+        retry.validate()?;
+    }
 
     // Execute pre transfer command
     if let Some(pre_job) = &profile.pre_transfer_command {
-        execute_command(pre_job, &profile.profile_id, "pre-transfer").await?;
+        let result = execute_command(pre_job, &profile.profile_id, "pre-transfer").await;
+        report.record_hook("pre-transfer", pre_job, &result);
+        result?;
     }
 
     // Execute transfer
-    let transfer_result: Result<()> = match profile.transfer_protocol.protocol {
+    let mut attempts = 0;
+    let transfer_result: Result<TransferOutcome> = match profile.transfer_protocol.protocol {
         ProtocolType::Sftp => {
             let handler = SftpHandler;
 
             match profile.source.kind {
-                SourceType::Local => handler.send(&profile).await,
-                SourceType::Sftp => handler.receive(&profile).await,
+                SourceType::Local => {
+                    with_retry(&profile, &context.cancel, || {
+                        attempts += 1;
+                        handler.send(Arc::clone(&profile), context.clone())
+                    })
+                    .await
+                },
+                SourceType::Sftp => {
+                    with_retry(&profile, &context.cancel, || {
+                        attempts += 1;
+                        handler.receive(Arc::clone(&profile), context.clone())
+                    })
+                    .await
+                },
                 _ => {
                     return Err(
                         AppError::Validation("Unsupported transfer source type".into()).into(),
@@ -34,10 +91,28 @@ pub async fn process_transfer_profile(profile: TransferProfile) -> Result<()> {
         // Future protocols (e.g. Scp, Http, etc.)
         ProtocolType::Scp => {
             let handler = ScpHandler;
+            if profile.resume.unwrap_or(false) {
+                warn!(
+                    "'resume' is only supported for SFTP. Profile '{}' will transfer from the beginning.",
+                    profile.profile_id
+                );
+            }
 
             match profile.source.kind {
-                SourceType::Local => handler.send(&profile).await,
-                SourceType::Scp => handler.receive(&profile).await,
+                SourceType::Local => {
+                    with_retry(&profile, &context.cancel, || {
+                        attempts += 1;
+                        handler.send(Arc::clone(&profile), context.clone())
+                    })
+                    .await
+                },
+                SourceType::Scp => {
+                    with_retry(&profile, &context.cancel, || {
+                        attempts += 1;
+                        handler.receive(Arc::clone(&profile), context.clone())
+                    })
+                    .await
+                },
                 _ => {
                     return Err(
                         AppError::Validation("Unsupported transfer source type".into()).into(),
@@ -49,21 +124,50 @@ pub async fn process_transfer_profile(profile: TransferProfile) -> Result<()> {
           // }
     };
 
+    report.attempts = attempts;
+
+    // libssh2やI/Oのタイムアウトは AppError::Timeout として扱う
+    // Treat libssh2 and I/O timeouts as AppError::Timeout
+    let operation_secs = context.timeouts(&profile).operation_secs();
+    let transfer_result = transfer_result.map_err(|e| {
+        surface_timeout(
+            e,
+            &format!("transferring files for profile '{}'", profile.profile_id),
+            operation_secs,
+        )
+    });
+
     // Execute post transfer or on-error command
     match transfer_result {
-        Ok(_) => {
+        Ok(outcome) => {
             // 転送が成功した場合
             // If the transfer was successful
             info!(
-                "File transfer completed successfully for profile '{}'.",
-                profile.profile_id
+                "File transfer completed successfully for profile '{}': {} file(s), {} bytes in {:.1}s.",
+                profile.profile_id,
+                outcome.files.len(),
+                outcome.bytes,
+                outcome.duration.as_secs_f64()
             );
+            report.record_outcome(&outcome);
+            // 転送元ファイルの後処理（削除・移動など）。リモートの転送元は転送に使ったセッション上で行うため、
+            // ブロッキングプールで実行する
+            // Post-processing of the source files (delete, move, etc.).
+            // Remote sources are handled over the session used for the transfer, so it runs on the blocking pool
+            let dispose_profile = Arc::clone(&profile);
+            tokio::task::spawn_blocking(move || {
+                dispose_sources(&dispose_profile, &outcome.files, outcome.source_session.as_ref())
+            })
+                .await
+                .context("Source disposition task terminated unexpectedly")??;
             if let Some(post_job) = &profile.post_transfer_command {
                 // post_job が失敗しても、転送自体は成功なので、エラーとして返すかどうかは要件次第
                 // ここでは post_job の失敗もエラーとして伝播させる。
                 // Even if post_job fails, the transfer itself is successful, so whether to return it as an error is up to your requirements.
                 // Here, we'll also propagate post_job failures as errors.
-                execute_command(post_job, &profile.profile_id, "post-transfer").await?
+                let result = execute_command(post_job, &profile.profile_id, "post-transfer").await;
+                report.record_hook("post-transfer", post_job, &result);
+                result?;
             }
             Ok(())
         }
@@ -83,7 +187,9 @@ pub async fn process_transfer_profile(profile: TransferProfile) -> Result<()> {
                 // Even if on_error_job fails here, the original transfer error is given priority and logged,
                 // Consider whether to return the original error or the on_error_job error.
                 // Generally, the original error is reported and the success/failure of on_error_job is also logged.
-                match execute_command(on_error_job, &profile.profile_id, "on-error").await {
+                let result = execute_command(on_error_job, &profile.profile_id, "on-error").await;
+                report.record_hook("on-error", on_error_job, &result);
+                match result {
                     Ok(_) => {
                         info!(
                             "On-error command executed successfully for profile '{}'.",
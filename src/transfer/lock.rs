@@ -0,0 +1,197 @@
+use crate::{AppError, TransferContext};
+use anyhow::{Context, Result};
+use etcetera::{choose_base_strategy, BaseStrategy};
+use log::{debug, warn};
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+// ロックの解放を待つ間の確認間隔
+// Interval between attempts while waiting for a lock to be released
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 同じプロファイルが複数のプロセスで同時に実行されないようにするアドバイザリロック。
+/// ロックファイルには保持しているプロセスのPIDを書き込み、ドロップ時に解放する。
+/// Advisory lock that keeps a profile from running in several processes at once.
+/// The lock file holds the PID of the owning process; the lock is released on drop.
+#[derive(Debug)]
+pub struct ProfileLock {
+    file: File,
+    path: PathBuf,
+}
+
+// ロックを取得できなかった場合の保持者（PIDが読めない場合はNone）
+// Holder of the lock when it could not be taken (None when no PID could be read)
+enum Attempt {
+    Acquired(ProfileLock),
+    Busy(Option<u32>),
+}
+
+impl ProfileLock {
+    /// 既定のロックディレクトリ（ランタイムディレクトリの vento、ない場合は一時ディレクトリの vento-<ユーザー>）
+    /// Default lock directory (vento in the runtime directory, or vento-<user> in the temp directory without one)
+    pub fn default_dir() -> PathBuf {
+        choose_base_strategy()
+            .ok()
+            .and_then(|strategy| strategy.runtime_dir())
+            .map(|dir| dir.join("vento"))
+            .unwrap_or_else(|| {
+                let user = std::env::var("USER")
+                    .or_else(|_| std::env::var("USERNAME"))
+                    .unwrap_or_default();
+                std::env::temp_dir().join(format!("vento-{}", user))
+            })
+    }
+
+    /// プロファイルのロックファイルのパス
+    /// Path of the profile's lock file
+    pub fn path_for(dir: &Path, profile_id: &str) -> PathBuf {
+        let name: String = profile_id
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect();
+        dir.join(format!("{}.lock", name))
+    }
+
+    /// `context` の設定に従ってプロファイルのロックを取得する。
+    /// 待機時間が未設定の場合、他のプロセスが保持していれば直ちに `AppError::ProfileLocked` を返す。
+    /// Take the profile's lock according to the settings in `context`.
+    /// Without a wait time, fails right away with `AppError::ProfileLocked` when another process holds it.
+    pub async fn acquire(profile_id: &str, context: &TransferContext) -> Result<ProfileLock> {
+        let dir = context
+            .options
+            .lock_dir
+            .clone()
+            .unwrap_or_else(ProfileLock::default_dir);
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create lock directory: '{}'", dir.display()))?;
+        let path = ProfileLock::path_for(&dir, profile_id);
+
+        let started = Instant::now();
+        let mut logged_wait = false;
+        loop {
+            let pid = match ProfileLock::try_acquire(&path)
+                .with_context(|| format!("Failed to lock '{}'", path.display()))?
+            {
+                Attempt::Acquired(lock) => {
+                    debug!("Acquired lock for profile '{}': {}", profile_id, path.display());
+                    return Ok(lock);
+                }
+                Attempt::Busy(pid) => pid,
+            };
+            let Some(wait) = context
+                .options
+                .lock_wait
+                .filter(|wait| started.elapsed() < *wait)
+            else {
+                return Err(AppError::ProfileLocked {
+                    profile_id: profile_id.to_string(),
+                    pid,
+                    path: path.display().to_string(),
+                }
+                .into());
+            };
+            if !logged_wait {
+                warn!(
+                    "Profile '{}' is locked by process {}; waiting up to {}s for it to finish.",
+                    profile_id,
+                    pid.map(|pid| pid.to_string()).unwrap_or_else(|| "unknown".to_string()),
+                    wait.as_secs()
+                );
+                logged_wait = true;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(LOCK_POLL_INTERVAL) => {}
+                _ = context.cancel.cancelled() => return Err(AppError::Cancelled.into()),
+            }
+        }
+    }
+
+    fn try_acquire(path: &Path) -> io::Result<Attempt> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let previous = read_pid(&mut file);
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(Attempt::Busy(previous)),
+            // ファイルロックに対応していないファイルシステムでは、記録されたPIDの生存だけで判断する
+            // On file systems without file locking, rely on whether the recorded PID is alive
+            Err(TryLockError::Error(e)) if e.kind() == io::ErrorKind::Unsupported => {
+                if let Some(pid) =
+                    previous.filter(|pid| *pid != std::process::id() && process_alive(*pid))
+                {
+                    return Ok(Attempt::Busy(Some(pid)));
+                }
+            }
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+        // 正常に終了したプロセスはPIDを消すため、残っているPIDは異常終了したプロセスのもの
+        // A process that exits cleanly clears its PID, so a leftover PID belongs to one that died
+        if let Some(pid) = previous.filter(|pid| !process_alive(*pid)) {
+            warn!(
+                "Removed a stale lock left by process {} (no longer running): {}",
+                pid,
+                path.display()
+            );
+        }
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        writeln!(file, "{}", std::process::id())?;
+        file.flush()?;
+        Ok(Attempt::Acquired(ProfileLock {
+            file,
+            path: path.to_path_buf(),
+        }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ProfileLock {
+    fn drop(&mut self) {
+        // ファイル自体は削除しない（待機中のプロセスが開いている可能性があるため）
+        // The file itself is kept, since a waiting process may already have it open
+        if let Err(e) = self.file.set_len(0) {
+            warn!("Failed to clear lock file '{}': {}", self.path.display(), e);
+        }
+        let _ = self.file.unlock();
+    }
+}
+
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+/// プロセスが実行中かどうか（判断できない環境では実行中とみなす）
+/// Whether a process is running (assumed running where this cannot be told)
+pub fn process_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        let Some(pid) = libc::pid_t::try_from(pid).ok().filter(|pid| *pid > 0) else {
+            return false;
+        };
+        // シグナル0は送信せずに存在だけを確認する（EPERMは他ユーザーのプロセスが存在することを示す）
+        // Signal 0 only checks for existence (EPERM means a process of another user exists)
+        let result = unsafe { libc::kill(pid, 0) };
+        result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        true
+    }
+}
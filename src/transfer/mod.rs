@@ -1,11 +1,35 @@
+pub mod atomic;
+pub mod disposition;
 pub mod handler;
+pub mod lock;
+pub mod progress;
 pub mod protocol;
+pub mod remote;
+pub mod report;
+pub mod resume;
+pub mod retry;
 pub mod scp;
+pub mod stability;
 pub mod sftp;
+pub mod throttle;
+pub mod verify;
+pub mod walk;
 
+pub use atomic::*;
+pub use disposition::*;
 pub use handler::*;
+pub use lock::*;
+pub use progress::*;
 pub use protocol::*;
+pub use remote::*;
+pub use report::*;
+pub use resume::*;
+pub use retry::*;
 pub use scp::*;
+pub use stability::*;
 pub use sftp::*;
+pub use throttle::*;
+pub use verify::*;
+pub use walk::*;
 
 pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024 * 1024; // 8MB
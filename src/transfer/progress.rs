@@ -0,0 +1,247 @@
+use crate::TransferContext;
+use indicatif::{BinaryBytes, HumanDuration, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::info;
+use std::{
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// 進捗イベントを通知する最小間隔（開始時と完了時は常に通知する）
+// Minimum interval between progress events (the first and the last are always emitted)
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 1ファイルの転送の進捗
+/// Progress of a single file transfer
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressEvent {
+    // 転送元のパス
+    // Path of the source file
+    pub file: PathBuf,
+    // 転送済みのバイト数（再開時は既に転送済みの部分を含む）
+    // Bytes done (including the part already present when resuming)
+    pub bytes_done: u64,
+    pub total_bytes: u64,
+    // 今回の転送の平均速度
+    // Average throughput of this transfer
+    pub bytes_per_sec: f64,
+    // 速度が計測できない場合はNone
+    // None while the throughput cannot be measured yet
+    pub eta: Option<Duration>,
+    pub finished: bool,
+}
+
+impl ProgressEvent {
+    pub fn percent(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 100.0;
+        }
+        self.bytes_done as f64 * 100.0 / self.total_bytes as f64
+    }
+}
+
+/// 進捗イベントを受け取るコールバック。`TransferContext::with_progress` で登録する。
+/// Callback receiving progress events. Registered through `TransferContext::with_progress`.
+pub trait ProgressObserver: Send + Sync {
+    fn on_progress(&self, event: &ProgressEvent);
+}
+
+impl<F> ProgressObserver for F
+where
+    F: Fn(&ProgressEvent) + Send + Sync,
+{
+    fn on_progress(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+/// 読み込んだバイト数を数え、一定間隔で進捗イベントを通知するリーダー
+/// Reader that counts the bytes read and emits progress events at a fixed interval
+pub struct ProgressReader<'a, R> {
+    inner: R,
+    context: &'a TransferContext,
+    file: PathBuf,
+    started: Instant,
+    // 読み込みを開始した位置（速度の計算から除く）
+    // Position the reader started at (excluded from the throughput)
+    initial: u64,
+    done: u64,
+    total: u64,
+    last_emit: Option<Instant>,
+    finished: bool,
+}
+
+impl<'a, R: Read> ProgressReader<'a, R> {
+    pub fn new(inner: R, context: &'a TransferContext, file: &Path, offset: u64, total: u64) -> Self {
+        ProgressReader {
+            inner,
+            context,
+            file: file.to_path_buf(),
+            started: Instant::now(),
+            initial: offset,
+            done: offset,
+            total,
+            last_emit: None,
+            finished: false,
+        }
+    }
+
+    fn emit(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+        let transferred = self.done.saturating_sub(self.initial);
+        let bytes_per_sec = if elapsed > 0.0 {
+            transferred as f64 / elapsed
+        } else {
+            0.0
+        };
+        let eta = (bytes_per_sec > 0.0).then(|| {
+            Duration::from_secs_f64(self.total.saturating_sub(self.done) as f64 / bytes_per_sec)
+        });
+        self.context.report_progress(&ProgressEvent {
+            file: self.file.clone(),
+            bytes_done: self.done,
+            total_bytes: self.total,
+            bytes_per_sec,
+            eta,
+            finished: self.finished,
+        });
+        self.last_emit = Some(now);
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if self.finished || self.context.progress.is_none() {
+            return Ok(n);
+        }
+        self.done += n as u64;
+        let now = Instant::now();
+        if n == 0 || self.done >= self.total {
+            self.finished = true;
+            self.emit(now);
+        } else if self
+            .last_emit
+            .is_none_or(|last| now.duration_since(last) >= PROGRESS_INTERVAL)
+        {
+            self.emit(now);
+        }
+        Ok(n)
+    }
+}
+
+/// 進捗を一定間隔のログ行として出力する（端末以外への出力向け）
+/// Writes progress as periodic log lines (for output that is not a terminal)
+#[derive(Debug)]
+pub struct LogProgress {
+    interval: Duration,
+    last_logged: Mutex<Option<Instant>>,
+}
+
+impl LogProgress {
+    pub fn new(interval: Duration) -> Self {
+        LogProgress {
+            interval,
+            last_logged: Mutex::new(None),
+        }
+    }
+}
+
+impl Default for LogProgress {
+    fn default() -> Self {
+        LogProgress::new(Duration::from_secs(10))
+    }
+}
+
+impl ProgressObserver for LogProgress {
+    fn on_progress(&self, event: &ProgressEvent) {
+        let now = Instant::now();
+        let mut last_logged = self.last_logged.lock().unwrap_or_else(|e| e.into_inner());
+        // 開始直後の通知は省き、間隔ごとと完了時だけ出力する
+        // Skip the initial event; log once per interval and on completion
+        match *last_logged {
+            _ if event.finished => *last_logged = None,
+            None => {
+                *last_logged = Some(now);
+                return;
+            }
+            Some(last) if now.duration_since(last) >= self.interval => *last_logged = Some(now),
+            Some(_) => return,
+        }
+        info!(
+            "Progress '{}': {} / {} ({:.1}%), {}/s, ETA {}",
+            event.file.display(),
+            BinaryBytes(event.bytes_done),
+            BinaryBytes(event.total_bytes),
+            event.percent(),
+            BinaryBytes(event.bytes_per_sec as u64),
+            event
+                .eta
+                .map(|eta| HumanDuration(eta).to_string())
+                .unwrap_or_else(|| "unknown".to_string())
+        );
+    }
+}
+
+/// 端末にファイルごとの進捗バーを描画する
+/// Draws a progress bar per file on the terminal
+#[derive(Debug, Default)]
+pub struct TerminalProgress {
+    current: Mutex<Option<(PathBuf, ProgressBar)>>,
+}
+
+impl TerminalProgress {
+    pub fn new() -> Self {
+        TerminalProgress::default()
+    }
+
+    fn new_bar(event: &ProgressEvent) -> ProgressBar {
+        let bar = ProgressBar::with_draw_target(
+            Some(event.total_bytes),
+            ProgressDrawTarget::stdout(),
+        );
+        let style = ProgressStyle::with_template(
+            "{msg} [{bar:30}] {binary_bytes}/{binary_total_bytes} {prefix}",
+        )
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ");
+        bar.set_style(style);
+        bar.set_message(
+            event
+                .file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| event.file.display().to_string()),
+        );
+        bar
+    }
+}
+
+impl ProgressObserver for TerminalProgress {
+    fn on_progress(&self, event: &ProgressEvent) {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        if current.as_ref().is_none_or(|(file, _)| file != &event.file) {
+            if let Some((_, bar)) = current.take() {
+                bar.abandon();
+            }
+            *current = Some((event.file.clone(), Self::new_bar(event)));
+        }
+        let Some((_, bar)) = current.as_ref() else {
+            return;
+        };
+        bar.set_position(event.bytes_done);
+        bar.set_prefix(format!(
+            "{}/s, ETA {}",
+            BinaryBytes(event.bytes_per_sec as u64),
+            event
+                .eta
+                .map(|eta| HumanDuration(eta).to_string())
+                .unwrap_or_else(|| "-".to_string())
+        ));
+        if event.finished {
+            bar.finish();
+            *current = None;
+        }
+    }
+}
@@ -1,8 +1,365 @@
-use crate::TransferProfile;
-use anyhow::Result;
+use crate::{
+    AppConfig, AppError, BandwidthLimit, ConnectionSettings, HostKeySettings, ProgressEvent,
+    ProgressObserver, SharedThrottle, TimeoutSettings, TransferProfile, VerifyMethod, DEFAULT_BUFFER_SIZE, DEFAULT_MAX_FILE_SIZE_MB,
+};
+use anyhow::{Context, Result};
+use log::debug;
+use serde::Serialize;
+use ssh2::Session;
+use std::{
+    fmt,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+// ライブラリ利用者がトークンを作成できるよう再公開する
+// Re-exported so that library users can create tokens
+pub use tokio_util::sync::CancellationToken;
 
+/// 転送に成功したファイル
+/// A file that was transferred successfully
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferredFile {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    // 転送元ルートからの相対パス（単一ファイルの場合はファイル名）
+    // Path relative to the source root (the file name for a single file)
+    pub relative: PathBuf,
+    // ファイル全体のサイズ（バイト）
+    // Size of the whole file (bytes)
+    pub size: u64,
+    // 今回の転送で送受信したバイト数（再開時は残りの部分のみ）
+    // Bytes sent or received by this transfer (only the remainder when resuming)
+    pub bytes: u64,
+    // 検証が有効な場合のファイル全体のチェックサム（16進数）
+    // Checksum of the whole file (hex) when verification is enabled
+    pub checksum: Option<String>,
+}
+
+impl TransferredFile {
+    pub fn single(source: &Path, destination: &Path) -> Self {
+        TransferredFile {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            relative: source.file_name().map(PathBuf::from).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+/// 1回の転送の結果
+/// Result of a single transfer
+#[derive(Clone, Default)]
+pub struct TransferOutcome {
+    pub files: Vec<TransferredFile>,
+    // 今回の転送で送受信した合計バイト数
+    // Total bytes sent or received by this transfer
+    pub bytes: u64,
+    pub duration: Duration,
+    // 転送元がリモートの場合、転送に使った転送元ホストへのセッション（転送元ファイルの処理に再利用する）
+    // Session to the source host used for the transfer when the source is remote
+    // (reused to dispose of the source files)
+    pub source_session: Option<Session>,
+}
+
+impl TransferOutcome {
+    pub fn new(files: Vec<TransferredFile>, duration: Duration) -> Self {
+        let bytes = files.iter().map(|file| file.bytes).sum();
+        TransferOutcome {
+            files,
+            bytes,
+            duration,
+            source_session: None,
+        }
+    }
+}
+
+impl fmt::Debug for TransferOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransferOutcome")
+            .field("files", &self.files)
+            .field("bytes", &self.bytes)
+            .field("duration", &self.duration)
+            .field("source_session", &self.source_session.is_some())
+            .finish()
+    }
+}
+
+/// 1回の実行に適用する設定。プロファイルで未設定の項目はここの値を使う。
+/// Settings applied to a single run. Items not set in the profile use these values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferOptions {
+    // ファイルサイズの上限（MB）。プロファイルの maxFileSizeMb が優先し、どちらもNoneか0なら無制限
+    // File size limit (MB). The profile's maxFileSizeMb takes precedence; unlimited when both are None or 0
+    pub max_file_size_mb: Option<u64>,
+    // ストリームの読み込みバッファのサイズ（バイト）
+    // Size of the read buffer for streams (bytes)
+    pub buffer_size: usize,
+    // 接続・操作のタイムアウト（プロファイルで未設定の項目に使う）
+    // Connection and operation timeouts (used for the fields the profile does not set)
+    pub timeouts: TimeoutSettings,
+    // ホスト鍵の検証設定（プロファイルとssh_configで未設定の項目に使う）
+    // Host key verification settings (used for the fields neither the profile nor ssh_config sets)
+    pub host_keys: HostKeySettings,
+    // プロファイルで verify が未指定の場合の検証方法
+    // Verification method when the profile does not set verify
+    pub verify: Option<VerifyMethod>,
+    // すべての転送に適用する速度の上限
+    // Rate cap applied to every transfer
+    pub bandwidth_cap: Option<BandwidthLimit>,
+    // プロファイルごとのロックを取得するかどうか（同時実行を許可する場合はfalse）
+    // Whether to take the per-profile lock (false when concurrent runs are allowed)
+    pub profile_lock: bool,
+    // ロックファイルを置くディレクトリ（Noneは `ProfileLock::default_dir`）
+    // Directory for lock files (None for `ProfileLock::default_dir`)
+    pub lock_dir: Option<PathBuf>,
+    // 他のプロセスがロックを保持している場合に待つ時間（Noneは待たずに失敗する）
+    // How long to wait while another process holds the lock (None fails right away)
+    pub lock_wait: Option<Duration>,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        TransferOptions {
+            max_file_size_mb: None,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            timeouts: TimeoutSettings::default(),
+            host_keys: HostKeySettings::default(),
+            verify: None,
+            bandwidth_cap: None,
+            profile_lock: true,
+            lock_dir: None,
+            lock_wait: None,
+        }
+    }
+}
+
+impl TransferOptions {
+    /// config.yamlの設定から作成する
+    /// Create from the settings in config.yaml
+    pub fn from_app_config(app_config: &AppConfig) -> Self {
+        TransferOptions {
+            max_file_size_mb: Some(
+                app_config
+                    .max_file_size_mb
+                    .unwrap_or(DEFAULT_MAX_FILE_SIZE_MB),
+            ),
+            timeouts: app_config.timeouts.clone().unwrap_or_default(),
+            host_keys: app_config.host_keys.clone().unwrap_or_default(),
+            bandwidth_cap: app_config.bandwidth_limit.clone(),
+            lock_dir: app_config.lock_dir.as_ref().map(PathBuf::from),
+            lock_wait: app_config
+                .lock_wait_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            ..Default::default()
+        }
+    }
+}
+
+/// 転送処理に渡す実行時の情報
+/// Runtime state handed to a transfer
+#[derive(Clone, Default)]
+pub struct TransferContext {
+    // キャンセルされると、実行中の転送はファイルの区切りや読み込みの途中で中断する
+    // Once cancelled, a running transfer stops between files or in the middle of a read
+    pub cancel: CancellationToken,
+    // 転送中のファイルの進捗を受け取るコールバック
+    // Callback receiving the progress of the file being transferred
+    pub progress: Option<Arc<dyn ProgressObserver>>,
+    pub options: TransferOptions,
+    // `options.bandwidth_cap` のバケット。この値を共有するすべての転送の合計速度を制限する
+    // Bucket for `options.bandwidth_cap`; limits the combined rate of every transfer sharing it
+    pub global_throttle: Option<SharedThrottle>,
+}
+
+impl fmt::Debug for TransferContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransferContext")
+            .field("cancel", &self.cancel)
+            .field("progress", &self.progress.is_some())
+            .field("options", &self.options)
+            .field("global_throttle", &self.global_throttle)
+            .finish()
+    }
+}
+
+impl TransferContext {
+    pub fn new(cancel: CancellationToken) -> Self {
+        TransferContext {
+            cancel,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_progress(mut self, observer: Arc<dyn ProgressObserver>) -> Self {
+        self.progress = Some(observer);
+        self
+    }
+
+    /// 設定を置き換え、`bandwidth_cap` のバケットを新しく作る
+    /// Replace the options and create a new bucket for `bandwidth_cap`
+    pub fn with_options(mut self, options: TransferOptions) -> Self {
+        self.global_throttle = options.bandwidth_cap.clone().map(SharedThrottle::new);
+        self.options = options;
+        self
+    }
+
+    pub fn with_bandwidth_cap(mut self, cap: Option<BandwidthLimit>) -> Self {
+        self.global_throttle = cap.clone().map(SharedThrottle::new);
+        self.options.bandwidth_cap = cap;
+        self
+    }
+
+    /// 全体の上限のバケットを、他の実行と共有するものに置き換える（`with_options` の後に呼ぶ）
+    /// Replace the global cap's bucket with one shared with other runs (call after `with_options`)
+    pub fn with_global_throttle(mut self, throttle: Option<SharedThrottle>) -> Self {
+        self.global_throttle = throttle;
+        self
+    }
+
+    pub fn with_max_file_size_mb(mut self, max_file_size_mb: Option<u64>) -> Self {
+        self.options.max_file_size_mb = max_file_size_mb;
+        self
+    }
+
+    /// プロファイルに適用されるファイルサイズの上限（MB、Noneは無制限）
+    /// File size limit that applies to the profile (MB, None for unlimited)
+    pub fn max_file_size_mb(&self, profile: &TransferProfile) -> Option<u64> {
+        profile
+            .max_file_size_mb
+            .or(self.options.max_file_size_mb)
+            .filter(|limit| *limit > 0)
+    }
+
+    pub fn ensure_size_allowed(
+        &self,
+        profile: &TransferProfile,
+        path: &Path,
+        size: u64,
+    ) -> Result<(), AppError> {
+        match self.max_file_size_mb(profile) {
+            Some(limit_mb) if size > limit_mb.saturating_mul(1024 * 1024) => {
+                Err(AppError::FileTooLarge {
+                    path: path.display().to_string(),
+                    limit_mb,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// プロファイルのタイムアウトに、未設定の項目を補ったもの
+    /// The profile's timeouts with unset fields filled from the options
+    pub fn timeouts(&self, profile: &TransferProfile) -> TimeoutSettings {
+        profile
+            .timeouts
+            .clone()
+            .unwrap_or_default()
+            .or(&self.options.timeouts)
+    }
+
+    /// 接続に適用するタイムアウトとホスト鍵の設定（ssh_configの値は接続時に補う）
+    /// Timeout and host key settings for a connection (ssh_config values are filled in when connecting)
+    pub fn connection_settings(&self, profile: &TransferProfile) -> ConnectionSettings {
+        ConnectionSettings {
+            timeouts: profile.timeouts.clone().unwrap_or_default(),
+            host_keys: profile.host_keys.clone().unwrap_or_default(),
+            default_timeouts: self.options.timeouts.clone(),
+            default_host_keys: self.options.host_keys.clone(),
+        }
+    }
+
+    /// プロファイルに適用される検証方法
+    /// Verification method that applies to the profile
+    pub fn verify_method<'a>(&'a self, profile: &'a TransferProfile) -> Option<&'a VerifyMethod> {
+        profile.verify.as_ref().or(self.options.verify.as_ref())
+    }
+
+    pub fn report_progress(&self, event: &ProgressEvent) {
+        if let Some(observer) = &self.progress {
+            observer.on_progress(event);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn ensure_not_cancelled(&self) -> Result<(), AppError> {
+        if self.is_cancelled() {
+            return Err(AppError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// 転送プロトコルの実装。
+/// 各メソッドはtokioのランタイムをブロックしないよう、ブロッキングな処理を `run_blocking` で実行する。
+/// A transfer protocol implementation.
+/// Methods must not block the tokio runtime; blocking work goes through `run_blocking`.
 #[async_trait::async_trait]
-pub trait TransferProtocolHandler {
-    async fn send(&self, profile: &TransferProfile) -> Result<()>;
-    async fn receive(&self, profile: &TransferProfile) -> Result<()>;
+pub trait TransferProtocolHandler: Send + Sync {
+    async fn send(
+        &self,
+        profile: Arc<TransferProfile>,
+        context: TransferContext,
+    ) -> Result<TransferOutcome>;
+    async fn receive(
+        &self,
+        profile: Arc<TransferProfile>,
+        context: TransferContext,
+    ) -> Result<TransferOutcome>;
+}
+
+/// ブロッキングな転送処理をtokioのブロッキングプールで実行し、結果を `TransferOutcome` にまとめる。
+/// 転送処理は転送したファイルと、転送元がリモートの場合はそのセッションを返す。
+/// キャンセル後に失敗した場合は `AppError::Cancelled` を返す。
+/// Run blocking transfer work on tokio's blocking pool and collect the result into a `TransferOutcome`.
+/// The work returns the transferred files and, when the source is remote, its session.
+/// Failures after cancellation are reported as `AppError::Cancelled`.
+pub async fn run_blocking<F>(context: TransferContext, transfer: F) -> Result<TransferOutcome>
+where
+    F: FnOnce(&TransferContext) -> Result<(Vec<TransferredFile>, Option<Session>)> + Send + 'static,
+{
+    context.ensure_not_cancelled()?;
+    let started = Instant::now();
+    let task_context = context.clone();
+    let result = tokio::task::spawn_blocking(move || transfer(&task_context))
+        .await
+        .context("Transfer task terminated unexpectedly")?;
+
+    match result {
+        Ok((files, source_session)) => Ok(TransferOutcome {
+            source_session,
+            ..TransferOutcome::new(files, started.elapsed())
+        }),
+        Err(e) if context.is_cancelled() => {
+            debug!("Transfer stopped after cancellation: {:#}", e);
+            Err(AppError::Cancelled.into())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 読み込みのたびにキャンセルされていないかを確認するリーダー
+/// Reader that checks for cancellation on every read
+pub struct CancellableReader<'a, R> {
+    inner: R,
+    context: &'a TransferContext,
+}
+
+impl<'a, R: Read> CancellableReader<'a, R> {
+    pub fn new(inner: R, context: &'a TransferContext) -> Self {
+        CancellableReader { inner, context }
+    }
+}
+
+impl<R: Read> Read for CancellableReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.context.ensure_not_cancelled().map_err(io::Error::other)?;
+        self.inner.read(buf)
+    }
 }
@@ -0,0 +1,56 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use ssh2::Session;
+use std::{
+    io::{self, Read},
+    path::Path,
+};
+
+/// 確立済みのセッション上でリモートコマンドを実行し、標準出力を返す
+/// Run a remote command over an established session and return its stdout
+pub fn exec_remote(session: &Session, command: &str) -> Result<String> {
+    let mut channel = session.channel_session()?;
+    channel.exec(command)?;
+    let mut output = String::new();
+    channel.read_to_string(&mut output)?;
+    channel.wait_close()?;
+    let status = channel.exit_status()?;
+    if status != 0 {
+        return Err(anyhow!(
+            "Remote command '{}' exited with status {}",
+            command,
+            status
+        ));
+    }
+    Ok(output)
+}
+
+/// リモートのPOSIXシェル向けにパスをシングルクォートで囲む
+/// Single-quote a path for a remote POSIX shell
+pub fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+/// 読み込みのたびに必要に応じてSSHキープアライブを送信するReader
+/// Reader that sends an SSH keepalive, when one is due, on every read
+pub struct KeepaliveReader<'a, R> {
+    inner: R,
+    session: &'a Session,
+}
+
+impl<'a, R: Read> KeepaliveReader<'a, R> {
+    pub fn new(inner: R, session: &'a Session) -> Self {
+        KeepaliveReader { inner, session }
+    }
+}
+
+impl<R: Read> Read for KeepaliveReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // 送信間隔はlibssh2側で管理されるため、ここでは毎回呼び出してよい
+        // libssh2 tracks the interval itself, so calling this on every read is fine
+        if let Err(e) = self.session.keepalive_send() {
+            debug!("Failed to send SSH keepalive: {}", e);
+        }
+        self.inner.read(buf)
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{AppError, TransferOutcome, TransferredFile};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::fmt;
+
+/// 転送の最終状態
+/// Final state of a transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransferStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// フックコマンド（pre-transfer / post-transfer / on-error）の実行結果
+/// Result of a hook command (pre-transfer / post-transfer / on-error)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookReport {
+    pub stage: String,
+    pub command: String,
+    // シグナルで終了した場合や起動できなかった場合はNone
+    // None when the command was killed by a signal or could not be started
+    pub exit_code: Option<i32>,
+    pub success: bool,
+}
+
+/// `process_transfer_profile` が返す、1回の実行の記録
+/// Record of a single run, returned by `process_transfer_profile`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferReport {
+    pub profile_id: String,
+    pub status: TransferStatus,
+    pub started_at: DateTime<Local>,
+    pub finished_at: Option<DateTime<Local>>,
+    // 転送の試行回数（再試行を含む）
+    // Number of transfer attempts (including retries)
+    pub attempts: u32,
+    pub files: Vec<TransferredFile>,
+    pub bytes: u64,
+    // 成功した試行の転送速度
+    // Throughput of the successful attempt
+    pub bytes_per_sec: f64,
+    pub hooks: Vec<HookReport>,
+    pub error: Option<String>,
+}
+
+impl TransferReport {
+    pub fn start(profile_id: &str) -> Self {
+        TransferReport {
+            profile_id: profile_id.to_string(),
+            status: TransferStatus::Running,
+            started_at: Local::now(),
+            finished_at: None,
+            attempts: 0,
+            files: Vec::new(),
+            bytes: 0,
+            bytes_per_sec: 0.0,
+            hooks: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn record_outcome(&mut self, outcome: &TransferOutcome) {
+        self.files = outcome.files.clone();
+        self.bytes = outcome.bytes;
+        let secs = outcome.duration.as_secs_f64();
+        self.bytes_per_sec = if secs > 0.0 {
+            outcome.bytes as f64 / secs
+        } else {
+            0.0
+        };
+    }
+
+    pub fn record_hook(&mut self, stage: &str, command: &str, result: &Result<Option<i32>>) {
+        let exit_code = match result {
+            Ok(code) => *code,
+            Err(e) => match e.downcast_ref::<AppError>() {
+                Some(AppError::CommandFailed { code, .. }) => *code,
+                _ => None,
+            },
+        };
+        self.hooks.push(HookReport {
+            stage: stage.to_string(),
+            command: command.to_string(),
+            exit_code,
+            success: result.is_ok(),
+        });
+    }
+
+    /// 終了時刻と最終状態を記録する
+    /// Record the finish time and the final state
+    pub fn finish(&mut self, error: Option<&anyhow::Error>) {
+        self.finished_at = Some(Local::now());
+        self.status = match error {
+            None => TransferStatus::Succeeded,
+            Some(e) if matches!(e.downcast_ref::<AppError>(), Some(AppError::Cancelled)) => {
+                TransferStatus::Cancelled
+            }
+            Some(_) => TransferStatus::Failed,
+        };
+        self.error = error.map(|e| format!("{:#}", e));
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize the transfer report")
+    }
+}
+
+// 失敗時はエラーのコンテキストとしてレポートを添付するため、その見出しになる
+// On failure the report is attached as error context, so this becomes the headline
+impl fmt::Display for TransferReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            TransferStatus::Running => write!(f, "Transfer for profile '{}' is running", self.profile_id),
+            TransferStatus::Succeeded => {
+                write!(f, "Transfer for profile '{}' succeeded", self.profile_id)
+            }
+            TransferStatus::Failed => write!(f, "Transfer for profile '{}' failed", self.profile_id),
+            TransferStatus::Cancelled => {
+                write!(f, "Transfer for profile '{}' was cancelled", self.profile_id)
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use log::{info, warn};
+use std::io::{Read, Seek, SeekFrom};
+
+/// 再開時に一致を確認する末尾バイト数
+/// Number of trailing bytes compared before resuming a transfer
+pub const RESUME_VERIFY_BYTES: u64 = 64 * 1024;
+
+/// 途中まで転送済みのファイルから再開位置を決定する。
+/// 転送先の末尾と転送元の同じ範囲が一致した場合のみ転送先のサイズを返し、
+/// それ以外（サイズ超過・不一致）は0（最初から転送）を返す。
+/// Determine the offset to resume from, given a partially transferred destination.
+/// Returns the destination size only when its tail matches the same range of the source;
+/// otherwise (larger than the source, or mismatching bytes) returns 0 to restart from scratch.
+pub fn resume_offset<P, S>(
+    partial: &mut P,
+    partial_len: u64,
+    source: &mut S,
+    source_len: u64,
+) -> Result<u64>
+where
+    P: Read + Seek,
+    S: Read + Seek,
+{
+    if partial_len == 0 {
+        return Ok(0);
+    }
+    if partial_len > source_len {
+        warn!(
+            "Existing destination ({} bytes) is larger than the source ({} bytes). Restarting transfer from the beginning.",
+            partial_len, source_len
+        );
+        return Ok(0);
+    }
+
+    let verify_len = partial_len.min(RESUME_VERIFY_BYTES);
+    let start = partial_len - verify_len;
+    let partial_tail = read_range(partial, start, verify_len)?;
+    let source_tail = read_range(source, start, verify_len)?;
+
+    if partial_tail != source_tail {
+        warn!(
+            "Existing destination does not match the source in bytes {}..{}. Restarting transfer from the beginning.",
+            start, partial_len
+        );
+        return Ok(0);
+    }
+
+    info!(
+        "Resuming transfer at byte {} of {} (verified {} overlapping bytes).",
+        partial_len, source_len, verify_len
+    );
+    Ok(partial_len)
+}
+
+fn read_range<R: Read + Seek>(reader: &mut R, start: u64, len: u64) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; len as usize];
+    reader.seek(SeekFrom::Start(start))?;
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
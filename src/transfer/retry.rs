@@ -0,0 +1,192 @@
+use crate::{is_timeout, AppError, ErrorClass, RetryPolicy, TransferProfile};
+use anyhow::Result;
+use log::{info, warn};
+use ssh2::ErrorCode;
+use std::{future::Future, io, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+/// 既定の最大試行回数
+/// Default maximum number of attempts
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// 既定の初回待ち時間（ミリ秒）
+/// Default delay before the first retry (milliseconds)
+pub const DEFAULT_INITIAL_DELAY_MS: u64 = 1000;
+/// 既定の待ち時間の倍率
+/// Default growth factor of the delay
+pub const DEFAULT_BACKOFF_FACTOR: f64 = 2.0;
+/// 既定の待ち時間の上限（ミリ秒）
+/// Default cap of the delay (milliseconds)
+pub const DEFAULT_MAX_DELAY_MS: u64 = 60_000;
+/// 既定のゆらぎの割合
+/// Default jitter fraction
+pub const DEFAULT_JITTER: f64 = 0.1;
+/// 既定で再試行するエラー分類
+/// Error classes retried by default
+pub const DEFAULT_RETRY_ON: [ErrorClass; 3] = [ErrorClass::Connect, ErrorClass::Timeout, ErrorClass::Io];
+
+// libssh2の接続・ソケット関連のエラーコード
+// libssh2 error codes related to the connection or socket
+const LIBSSH2_CONNECTION_ERRORS: [i32; 8] = [-1, -2, -3, -5, -7, -8, -13, -43];
+// libssh2の認証関連のエラーコード
+// libssh2 error codes related to authentication
+const LIBSSH2_AUTH_ERRORS: [i32; 3] = [-15, -18, -19];
+
+impl RetryPolicy {
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1)
+    }
+
+    pub fn is_retryable(&self, class: ErrorClass) -> bool {
+        match &self.retry_on {
+            Some(classes) => classes.contains(&class),
+            None => DEFAULT_RETRY_ON.contains(&class),
+        }
+    }
+
+    /// `attempt` 回目の失敗後の待ち時間（ゆらぎを含まない）
+    /// Delay after the `attempt`-th failure, before jitter is applied
+    pub fn base_delay(&self, attempt: u32) -> Duration {
+        let initial = self.initial_delay_ms.unwrap_or(DEFAULT_INITIAL_DELAY_MS) as f64;
+        let factor = self.backoff_factor.unwrap_or(DEFAULT_BACKOFF_FACTOR);
+        let max = self.max_delay_ms.unwrap_or(DEFAULT_MAX_DELAY_MS) as f64;
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = (initial * factor.powi(exponent)).min(max);
+        Duration::from_millis(delay as u64)
+    }
+
+    /// ゆらぎを加えた待ち時間
+    /// Delay with jitter applied
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let base = self.base_delay(attempt);
+        let jitter = self.jitter.unwrap_or(DEFAULT_JITTER).clamp(0.0, 1.0);
+        // [1 - jitter, 1 + jitter] の範囲でランダムに伸縮する
+        // Scale randomly within [1 - jitter, 1 + jitter]
+        let scale = 1.0 + jitter * (fastrand::f64() * 2.0 - 1.0);
+        base.mul_f64(scale)
+    }
+}
+
+/// エラーを再試行判定用の分類に振り分ける
+/// Sort an error into the class used for retry decisions
+pub fn classify_error(err: &anyhow::Error) -> ErrorClass {
+    if is_timeout(err) {
+        return ErrorClass::Timeout;
+    }
+    for cause in err.chain() {
+        if let Some(app_error) = cause.downcast_ref::<AppError>() {
+            return match app_error {
+                AppError::Io(e) => classify_io_error(e),
+                AppError::AuthenticationFailed(_)
+                | AppError::MissingPrivateKeyReference
+                | AppError::HostKeyVerification { .. } => ErrorClass::Auth,
+                AppError::ChecksumMismatch { .. } => ErrorClass::Verify,
+                AppError::FileTooLarge { .. } => ErrorClass::SizeLimit,
+                AppError::Timeout { .. } => ErrorClass::Timeout,
+                AppError::Validation(_)
+                | AppError::Yaml(_)
+                | AppError::EnvVarNotFound(_)
+                | AppError::MissingSchedule
+                | AppError::InvalidCronSchedule { .. } => ErrorClass::Validation,
+                AppError::CommandFailed { .. }
+                | AppError::Cancelled
+                | AppError::ProfileLocked { .. }
+                | AppError::SourceNotReady { .. } => ErrorClass::Other,
+            };
+        }
+        if let Some(ssh_error) = cause.downcast_ref::<ssh2::Error>() {
+            return match ssh_error.code() {
+                ErrorCode::Session(code) if LIBSSH2_AUTH_ERRORS.contains(&code) => {
+                    ErrorClass::Auth
+                }
+                ErrorCode::Session(code) if LIBSSH2_CONNECTION_ERRORS.contains(&code) => {
+                    ErrorClass::Connect
+                }
+                _ => ErrorClass::Io,
+            };
+        }
+        if let Some(io_error) = cause.downcast_ref::<io::Error>() {
+            return classify_io_error(io_error);
+        }
+    }
+    ErrorClass::Other
+}
+
+fn classify_io_error(err: &io::Error) -> ErrorClass {
+    match err.kind() {
+        io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::AddrNotAvailable
+        | io::ErrorKind::HostUnreachable
+        | io::ErrorKind::NetworkUnreachable
+        | io::ErrorKind::NetworkDown
+        | io::ErrorKind::BrokenPipe => ErrorClass::Connect,
+        _ => ErrorClass::Io,
+    }
+}
+
+/// プロファイルの `retry` 設定に従って転送処理を再試行する。
+/// `retry` が未指定の場合は1回だけ実行し、`cancel` がキャンセルされた場合は再試行しない。
+/// Run the transfer step, retrying it according to the profile's `retry` settings.
+/// Runs exactly once when `retry` is not set, and stops retrying once `cancel` is cancelled.
+pub async fn with_retry<T, F, Fut>(
+    profile: &TransferProfile,
+    cancel: &CancellationToken,
+    mut operation: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let policy = profile.retry.clone().unwrap_or(RetryPolicy {
+        max_attempts: Some(1),
+        ..Default::default()
+    });
+    let max_attempts = policy.max_attempts();
+
+    let mut attempt = 1;
+    loop {
+        info!(
+            "Transfer attempt {}/{} for profile '{}'.",
+            attempt, max_attempts, profile.profile_id
+        );
+        let err = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        // キャンセルされた転送は再試行しない
+        // A cancelled transfer is never retried
+        if cancel.is_cancelled() || matches!(err.downcast_ref::<AppError>(), Some(AppError::Cancelled)) {
+            return Err(err);
+        }
+
+        let class = classify_error(&err);
+        if attempt >= max_attempts || !policy.is_retryable(class) {
+            if attempt < max_attempts {
+                warn!(
+                    "Transfer attempt {}/{} for profile '{}' failed with a non-retryable {} error.",
+                    attempt, max_attempts, profile.profile_id, class
+                );
+            }
+            return Err(err);
+        }
+
+        let delay = policy.delay(attempt);
+        warn!(
+            "Transfer attempt {}/{} for profile '{}' failed ({} error): {:#}. Retrying in {:.1}s.",
+            attempt,
+            max_attempts,
+            profile.profile_id,
+            class,
+            err,
+            delay.as_secs_f64()
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = cancel.cancelled() => return Err(AppError::Cancelled.into()),
+        }
+        attempt += 1;
+    }
+}
@@ -1,85 +1,251 @@
 use crate::{
-    connect_session_and_authenticate, get_max_file_size_mb, transfer::protocol::TransferProtocolHandler, TransferProfile
+    commit_local_file, commit_remote_file, connect_session_and_authenticate,
+    ensure_checksums_match, ensure_remote_dir,
+    is_remote_dir, local_file_state, remote_checksum, remote_file_state, retain_ready_files,
+    run_blocking, transfer::protocol::TransferProtocolHandler, wait_until_ready,
+    walk_local_dir, walk_remote_dir, CancellableReader, Checksum, DirTree, HashingReader, KeepaliveReader,
+    ProgressReader, SourceLayout, Throttle, ThrottledReader,
+    TransferContext, TransferOutcome, TransferredFile, TransferProfile,
 };
-use anyhow::{anyhow, Context, Result};
-use log::info;
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
 use ssh2::Session;
 use std::{
-    fs::File, io::{copy, BufReader}, path::{Path, PathBuf}
+    fs::{self, File}, io::{copy, BufReader}, path::{Path, PathBuf}, sync::Arc,
 };
 
 pub struct ScpHandler;
 
 #[async_trait::async_trait]
 impl TransferProtocolHandler for ScpHandler {
-    async fn send(&self, profile: &TransferProfile) -> Result<()> {
-        let protocol = profile.transfer_protocol.protocol.to_string();
-        info!(
-            "Attempting to send file from '{}' to {:?} destination '{}'@{}:{}{}",
-            profile.source.path,
-            profile.transfer_protocol.protocol,
-            profile
-                .destination
-                .authentication
-                .as_ref()
-                .map_or("unknown", |a| a.username.as_str()),
-            profile.destination.host.as_deref().unwrap_or("localhost"),
-            profile.destination.port.unwrap_or(22),
-            profile.destination.path
-        );
-        let session = connect_session_and_authenticate(
-           &protocol, 
-            profile.destination.authentication.as_ref(),
-            profile.destination.host.as_deref(),
-            profile.destination.port,
-        )?;
-
-        transfer_file_scp(
-            &session,
-            &profile.source.path.clone().into(),
-            &profile.destination.path.clone().into(),
-            true
-        )
+    async fn send(
+        &self,
+        profile: Arc<TransferProfile>,
+        context: TransferContext,
+    ) -> Result<TransferOutcome> {
+        run_blocking(context, move |context| Ok((send_scp(&profile, context)?, None))).await
+    }
 
+    async fn receive(
+        &self,
+        profile: Arc<TransferProfile>,
+        context: TransferContext,
+    ) -> Result<TransferOutcome> {
+        run_blocking(context, move |context| receive_scp(&profile, context)).await
     }
+}
 
-    async fn receive(&self, profile: &TransferProfile) -> Result<()> {
-        let protocol = profile.transfer_protocol.protocol.to_string();
-        info!(
-            "Attempting to receive file from {} source '{}'@{}:{}{} to local '{}'",
-            &protocol,
-            profile
-                .source
-                .authentication
-                .as_ref()
-                .map_or("unknown", |a| a.username.as_str()),
-            profile.source.host.as_deref().unwrap_or("localhost"),
-            profile.source.port.unwrap_or(22),
-            profile.source.path,
-            profile.destination.path
-        );
+fn send_scp(profile: &TransferProfile, context: &TransferContext) -> Result<Vec<TransferredFile>> {
+    let protocol = profile.transfer_protocol.protocol.to_string();
+    info!(
+        "Attempting to send file from '{}' to {:?} destination '{}'@{}:{}{}",
+        profile.source.path,
+        profile.transfer_protocol.protocol,
+        profile
+            .destination
+            .authentication
+            .as_ref()
+            .map_or("unknown", |a| a.username.as_str()),
+        profile.destination.host.as_deref().unwrap_or("localhost"),
+        profile.destination.port.unwrap_or(22),
+        profile.destination.path
+    );
+    let session = connect_session_and_authenticate(
+       &protocol, 
+        profile.destination.authentication.as_ref(),
+        profile.destination.host.as_deref(),
+        profile.destination.port,
+        &context.connection_settings(profile),
+    )?;
+
+    let dst = PathBuf::from(&profile.destination.path);
+
+    match SourceLayout::resolve(profile, |path| path.is_dir())? {
+        SourceLayout::File(src) => {
+            wait_until_ready(profile, context, &src, local_file_state)?;
+            let file = transfer_file_scp(&session, profile, context, &src, &dst, true)?;
+            Ok(vec![file])
+        }
+        SourceLayout::Tree {
+            root,
+            recursive,
+            matcher,
+        } => {
+            info!(
+                "Attempting to upload directory '{}' (recursive: {}) to remote path '{}'",
+                root.display(),
+                recursive,
+                dst.display()
+            );
+            let mut tree = walk_local_dir(&root, recursive)?.matching(matcher.as_ref());
+            retain_ready_files(profile, context, &root, &mut tree, local_file_state)?;
+            upload_tree_scp(&session, profile, context, &root, &dst, &tree)
+        }
+    }
+}
+
+// 転送したファイルと、転送元ファイルの処理に再利用するセッションを返す
+// Returns the transferred files and the session reused to dispose of the source files
+fn receive_scp(
+    profile: &TransferProfile,
+    context: &TransferContext,
+) -> Result<(Vec<TransferredFile>, Option<Session>)> {
+    let protocol = profile.transfer_protocol.protocol.to_string();
+    info!(
+        "Attempting to receive file from {} source '{}'@{}:{}{} to local '{}'",
+        &protocol,
+        profile
+            .source
+            .authentication
+            .as_ref()
+            .map_or("unknown", |a| a.username.as_str()),
+        profile.source.host.as_deref().unwrap_or("localhost"),
+        profile.source.port.unwrap_or(22),
+        profile.source.path,
+        profile.destination.path
+    );
+
+    let session = connect_session_and_authenticate(
+        &protocol,
+        profile.source.authentication.as_ref(),
+        profile.source.host.as_deref(),
+        profile.source.port,
+        &context.connection_settings(profile),
+    )?;
 
-        let session = connect_session_and_authenticate(
-            &protocol,
-            profile.source.authentication.as_ref(),
-            profile.source.host.as_deref(),
-            profile.source.port,
-        )?;
-
-        transfer_file_scp(
-            &session,
-            &profile.source.path.clone().into(),
-            &profile.destination.path.clone().into(),
-            false
-        )
+    let dst = PathBuf::from(&profile.destination.path);
+
+    // SCP自体にはディレクトリ操作がないため、一覧取得にはSFTPサブシステムを利用する
+    // SCP has no directory operations, so the SFTP subsystem is used for listing
+    let sftp = session.sftp().ok();
+    let layout = SourceLayout::resolve(profile, |path| {
+        sftp.as_ref().is_some_and(|sftp| is_remote_dir(sftp, path))
+    })?;
+
+    match layout {
+        SourceLayout::File(src) => {
+            wait_until_ready(profile, context, &src, |path| {
+                let sftp = sftp.as_ref().context(
+                    "Checking the stability of remote files over SCP requires the SFTP subsystem",
+                )?;
+                remote_file_state(sftp, path)
+            })?;
+            let file = transfer_file_scp(&session, profile, context, &src, &dst, false)?;
+            Ok((vec![file], Some(session)))
+        }
+        SourceLayout::Tree {
+            root,
+            recursive,
+            matcher,
+        } => {
+            let sftp = sftp.context(
+                "Downloading multiple files over SCP requires the SFTP subsystem to list remote directories",
+            )?;
+            info!(
+                "Attempting to download remote directory '{}' (recursive: {}) to local path '{}'",
+                root.display(),
+                recursive,
+                dst.display()
+            );
+            let mut tree = walk_remote_dir(&sftp, &root, recursive)?.matching(matcher.as_ref());
+            retain_ready_files(profile, context, &root, &mut tree, |path| {
+                remote_file_state(&sftp, path)
+            })?;
+            let files = download_tree_scp(&session, profile, context, &root, &dst, &tree)?;
+            Ok((files, Some(session)))
+        }
     }
 }
 
-fn transfer_file_scp(session: &Session, src: &PathBuf, dst: &PathBuf, upload: bool) -> Result<()> {
-    use super::DEFAULT_BUFFER_SIZE;
+// ローカルディレクトリ配下のファイルをリモートへミラーする
+// Mirror the files under a local directory to the remote destination
+fn upload_tree_scp(
+    session: &Session,
+    profile: &TransferProfile,
+    context: &TransferContext,
+    src: &Path,
+    dst: &Path,
+    tree: &DirTree,
+) -> Result<Vec<TransferredFile>> {
+    if tree.files.is_empty() {
+        warn!("No files to upload were found in '{}'", src.display());
+    }
+    let sftp = session
+        .sftp()
+        .context("Uploading multiple files over SCP requires the SFTP subsystem to create remote directories")?;
+
+    ensure_remote_dir(&sftp, dst)?;
+    for dir in &tree.dirs {
+        ensure_remote_dir(&sftp, &dst.join(dir))?;
+    }
+    let mut transferred = Vec::with_capacity(tree.files.len());
+    for file in &tree.files {
+        let (src_file, dst_file) = (src.join(file), dst.join(file));
+        let mut transferred_file =
+            transfer_file_scp(session, profile, context, &src_file, &dst_file, true)?;
+        transferred_file.relative = file.clone();
+        transferred.push(transferred_file);
+    }
 
-    println!("scp start");
-    let max_mb = get_max_file_size_mb(); 
+    info!(
+        "Successfully uploaded {} file(s) from directory '{}' to '{}'",
+        tree.files.len(),
+        src.display(),
+        dst.display()
+    );
+    Ok(transferred)
+}
+
+// リモートディレクトリ配下のファイルをローカルへミラーする
+// Mirror the files under a remote directory to the local destination
+fn download_tree_scp(
+    session: &Session,
+    profile: &TransferProfile,
+    context: &TransferContext,
+    src: &Path,
+    dst: &Path,
+    tree: &DirTree,
+) -> Result<Vec<TransferredFile>> {
+    if tree.files.is_empty() {
+        warn!("No files to download were found in remote directory '{}'", src.display());
+    }
+
+    fs::create_dir_all(dst)
+        .with_context(|| format!("Failed to create local directory: '{}'", dst.display()))?;
+    for dir in &tree.dirs {
+        let local_dir = dst.join(dir);
+        fs::create_dir_all(&local_dir).with_context(|| {
+            format!("Failed to create local directory: '{}'", local_dir.display())
+        })?;
+    }
+    let mut transferred = Vec::with_capacity(tree.files.len());
+    for file in &tree.files {
+        let (src_file, dst_file) = (src.join(file), dst.join(file));
+        let mut transferred_file =
+            transfer_file_scp(session, profile, context, &src_file, &dst_file, false)?;
+        transferred_file.relative = file.clone();
+        transferred.push(transferred_file);
+    }
+
+    info!(
+        "Successfully downloaded {} file(s) from remote directory '{}' to '{}'",
+        tree.files.len(),
+        src.display(),
+        dst.display()
+    );
+    Ok(transferred)
+}
+
+fn transfer_file_scp(
+    session: &Session,
+    profile: &TransferProfile,
+    context: &TransferContext,
+    src: &Path,
+    dst: &Path,
+    upload: bool,
+) -> Result<TransferredFile> {
+    context.ensure_not_cancelled()?;
+    debug!("scp start");
     if upload {
         info!(
             "Attempting to upload file from '{}' to remote path '{}'",
@@ -95,43 +261,83 @@ fn transfer_file_scp(session: &Session, src: &PathBuf, dst: &PathBuf, upload: bo
 
         let metadata = local_file.metadata()?;
         let file_size = metadata.len();
-        let max_size_bytes = max_mb * 1024 * 1024;
-        if file_size > max_size_bytes {
-            return Err(anyhow!(
-                "File '{}' exceeds max allowed size ({} MB)",
-                src.display(),
-                max_size_bytes / 1024 / 1024
-            ));
+        context.ensure_size_allowed(profile, src, file_size)?;
+
+        // アトミック配信の場合は一時ファイルに書き込み、完了後にリネームする
+        // With atomic delivery, write to a temporary file and rename it once complete
+        let temp_path = profile.atomic.as_ref().map(|atomic| atomic.temp_path(dst));
+        let write_path = temp_path.as_deref().unwrap_or(dst);
+        if temp_path.is_some() {
+            info!("Writing to temporary remote file '{}'", write_path.display());
         }
 
-        let mut remote_file = session.scp_send(Path::new(dst), 0o644, file_size, None).with_context(|| {
+        let mut remote_file = session.scp_send(write_path, 0o644, file_size, None).with_context(|| {
             format!(
             "Failed to create remote destination file for upload: '{}'",
-                dst.display()
+                write_path.display()
             )
         })?;
         // Permissions on sent files are set to 0o644 (owner: read/write, group: read, other: read).
         // The file transfer timeout is set to 10 seconds.
         // No special callback processing is performed during file transfer.
-        let mut reader = BufReader::with_capacity(DEFAULT_BUFFER_SIZE, local_file); // 8MB buffer
-        copy(&mut reader, &mut remote_file).with_context(|| {
+        let mut checksum = context.verify_method(profile).map(Checksum::new);
+        let mut reader = HashingReader::new(
+            KeepaliveReader::new(
+                ProgressReader::new(
+                    ThrottledReader::new(
+                        CancellableReader::new(
+                            BufReader::with_capacity(context.options.buffer_size, local_file),
+                            context,
+                        ),
+                        Throttle::for_profile(profile, context),
+                    ),
+                    context,
+                    src,
+                    0,
+                    file_size,
+                ),
+                session,
+            ),
+            checksum.as_mut(),
+        );
+        let bytes = copy(&mut reader, &mut remote_file).with_context(|| {
             format!(
             "Failed to copy data during upload from '{}' to '{}'",
                 src.display(),
                 dst.display()
             )
         })?;
+        drop(reader);
 
         // Close the channel and wait for the whole content to be transferred
         remote_file.send_eof()?;
         remote_file.wait_eof()?;
         remote_file.close()?;
         remote_file.wait_close()?;
+
+        let checksum = match (context.verify_method(profile), checksum) {
+            (Some(method), Some(checksum)) => {
+                let local = checksum.finalize();
+                let remote = remote_checksum(session, session.sftp().ok().as_ref(), write_path, method)?;
+                ensure_checksums_match(write_path, method, &local, &remote)?;
+                Some(local)
+            }
+            _ => None,
+        };
+        if let Some(temp_path) = &temp_path {
+            commit_remote_file(session, session.sftp().ok().as_ref(), temp_path, dst)?;
+        }
         info!(
             "Successfully uploaded file from '{}' to '{}'",
             src.display(),
             dst.display()
         );
+        Ok(TransferredFile {
+            size: file_size,
+            bytes,
+            checksum,
+            ..TransferredFile::single(src, dst)
+        })
     } else {
         info!(
             "Attempting to download file from remote path '{}' to local path '{}'",
@@ -146,31 +352,77 @@ fn transfer_file_scp(session: &Session, src: &PathBuf, dst: &PathBuf, upload: bo
         })?;
 
         let file_size = stat.size(); // ファイルサイズ (u64)
-        let max_size_bytes = max_mb * 1024 * 1024;
-
-        if file_size > max_size_bytes {
-            return Err(anyhow!(
-                "Remote file '{}' exceeds max allowed size ({} MB)",
-                src.display(),
-                max_size_bytes / 1024 / 1024
-            ));
-        }
+        context.ensure_size_allowed(profile, src, file_size)?;
         
-        let mut local_file = File::create(dst)?;
-        let mut reader = BufReader::with_capacity(DEFAULT_BUFFER_SIZE, remote_file); // 8MB buffer
-        copy(&mut reader, &mut local_file).with_context(|| {
+        // アトミック配信の場合は転送先ディレクトリ内の一時ファイルに書き込む
+        // With atomic delivery, write to a temporary file in the destination directory
+        let temp_file = profile
+            .atomic
+            .as_ref()
+            .map(|atomic| atomic.local_temp_file(dst))
+            .transpose()?;
+        let mut local_file = match &temp_file {
+            Some(temp_file) => {
+                info!(
+                    "Writing to temporary local file '{}'",
+                    temp_file.path().display()
+                );
+                temp_file.as_file().try_clone()?
+            }
+            None => File::create(dst)?,
+        };
+        let mut checksum = context.verify_method(profile).map(Checksum::new);
+        let mut reader = HashingReader::new(
+            KeepaliveReader::new(
+                ProgressReader::new(
+                    ThrottledReader::new(
+                        CancellableReader::new(
+                            BufReader::with_capacity(context.options.buffer_size, remote_file),
+                            context,
+                        ),
+                        Throttle::for_profile(profile, context),
+                    ),
+                    context,
+                    src,
+                    0,
+                    file_size,
+                ),
+                session,
+            ),
+            checksum.as_mut(),
+        );
+        let bytes = copy(&mut reader, &mut local_file).with_context(|| {
             format!("Failed to copy data during download from '{}' to '{}'",
                 src.display(),
                 dst.display()
             )
         })?;
+        drop(reader);
+
+        let checksum = match (context.verify_method(profile), checksum) {
+            (Some(method), Some(checksum)) => {
+                let local = checksum.finalize();
+                let remote = remote_checksum(session, session.sftp().ok().as_ref(), src, method)?;
+                ensure_checksums_match(src, method, &local, &remote)?;
+                Some(local)
+            }
+            _ => None,
+        };
+        if let Some(temp_file) = temp_file {
+            drop(local_file);
+            commit_local_file(temp_file, dst)?;
+        }
 
         info!(
             "Successfully downloaded file from '{}' to '{}'",
             src.display(),
             dst.display()
         );
+        Ok(TransferredFile {
+            size: file_size,
+            bytes,
+            checksum,
+            ..TransferredFile::single(src, dst)
+        })
     }
-
-    Ok(())
 }
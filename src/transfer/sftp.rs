@@ -1,85 +1,238 @@
 use crate::{
-    connect_session_and_authenticate, get_max_file_size_mb, transfer::protocol::TransferProtocolHandler, TransferProfile
+    commit_local_file, commit_remote_file, connect_session_and_authenticate, ensure_checksums_match, ensure_remote_dir,
+    is_remote_dir, local_file_state, remote_checksum, remote_file_state, resume_offset,
+    retain_ready_files, run_blocking, wait_until_ready,
+    transfer::protocol::TransferProtocolHandler, walk_local_dir, walk_remote_dir, CancellableReader, Checksum,
+    DirTree, HashingReader, KeepaliveReader, ProgressReader, SourceLayout, Throttle, ThrottledReader,
+    TransferContext, TransferOutcome, TransferredFile,
+    TransferProfile,
 };
 use anyhow::{anyhow, Context, Result};
-use log::info;
-use ssh2::Sftp;
+use log::{info, warn};
+use ssh2::{OpenFlags, OpenType, Session, Sftp};
 use std::{
-    fs::File,
-    io::copy,
+    fs::{self, File, OpenOptions},
+    io::{copy, BufReader, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 pub struct SftpHandler;
 
 #[async_trait::async_trait]
 impl TransferProtocolHandler for SftpHandler {
-    async fn send(&self, profile: &TransferProfile) -> Result<()> {
-        let protocol = profile.transfer_protocol.protocol.to_string();
-        info!(
-            "Attempting to send file from '{}' to SFTP destination '{}'@{}:{}{}",
-            profile.source.path,
-            profile
-                .destination
-                .authentication
-                .as_ref()
-                .map_or("unknown", |a| a.username.as_str()),
-            profile.destination.host.as_deref().unwrap_or("localhost"),
-            profile.destination.port.unwrap_or(22),
-            profile.destination.path
-        );
-        let session = connect_session_and_authenticate(
-            &protocol,
-            profile.destination.authentication.as_ref(),
-            profile.destination.host.as_deref(),
-            profile.destination.port,
-        )?;
-
-        let sftp = session.sftp()?;
-
-        transfer_file_sftp(
-            &sftp,
-            &profile.source.path.clone().into(),
-            &profile.destination.path.clone().into(),
-            true, // upload
-        )
+    async fn send(
+        &self,
+        profile: Arc<TransferProfile>,
+        context: TransferContext,
+    ) -> Result<TransferOutcome> {
+        run_blocking(context, move |context| Ok((send_sftp(&profile, context)?, None))).await
     }
 
-    async fn receive(&self, profile: &TransferProfile) -> Result<()> {
-        let protocol = profile.transfer_protocol.protocol.to_string();
-        info!(
-            "Attempting to receive file from SFTP source '{}'@{}:{}{} to local '{}'",
-            profile
-                .source
-                .authentication
-                .as_ref()
-                .map_or("unknown", |a| a.username.as_str()),
-            profile.source.host.as_deref().unwrap_or("localhost"),
-            profile.source.port.unwrap_or(22),
-            profile.source.path,
-            profile.destination.path
-        );
-        let session = connect_session_and_authenticate(
-            &protocol,
-            profile.source.authentication.as_ref(),
-            profile.source.host.as_deref(),
-            profile.source.port,
-        )?;
-
-        let sftp = session.sftp()?;
-
-        transfer_file_sftp(
-            &sftp,
-            &profile.source.path.clone().into(),
-            &profile.destination.path.clone().into(),
-            false, // download
-        )
+    async fn receive(
+        &self,
+        profile: Arc<TransferProfile>,
+        context: TransferContext,
+    ) -> Result<TransferOutcome> {
+        run_blocking(context, move |context| receive_sftp(&profile, context)).await
+    }
+}
+
+fn send_sftp(profile: &TransferProfile, context: &TransferContext) -> Result<Vec<TransferredFile>> {
+    let protocol = profile.transfer_protocol.protocol.to_string();
+    info!(
+        "Attempting to send file from '{}' to SFTP destination '{}'@{}:{}{}",
+        profile.source.path,
+        profile
+            .destination
+            .authentication
+            .as_ref()
+            .map_or("unknown", |a| a.username.as_str()),
+        profile.destination.host.as_deref().unwrap_or("localhost"),
+        profile.destination.port.unwrap_or(22),
+        profile.destination.path
+    );
+    let session = connect_session_and_authenticate(
+        &protocol,
+        profile.destination.authentication.as_ref(),
+        profile.destination.host.as_deref(),
+        profile.destination.port,
+        &context.connection_settings(profile),
+    )?;
+
+    let sftp = session.sftp()?;
+    let dst = PathBuf::from(&profile.destination.path);
+
+    match SourceLayout::resolve(profile, |path| path.is_dir())? {
+        SourceLayout::File(src) => {
+            wait_until_ready(profile, context, &src, local_file_state)?;
+            let file = transfer_file_sftp(&session, &sftp, profile, context, &src, &dst, true)?; // upload
+            Ok(vec![file])
+        }
+        SourceLayout::Tree {
+            root,
+            recursive,
+            matcher,
+        } => {
+            info!(
+                "Attempting to upload directory '{}' (recursive: {}) to remote path '{}'",
+                root.display(),
+                recursive,
+                dst.display()
+            );
+            let mut tree = walk_local_dir(&root, recursive)?.matching(matcher.as_ref());
+            retain_ready_files(profile, context, &root, &mut tree, local_file_state)?;
+            upload_tree_sftp(&session, &sftp, profile, context, &root, &dst, &tree)
+        }
+    }
+}
+
+// 転送したファイルと、転送元ファイルの処理に再利用するセッションを返す
+// Returns the transferred files and the session reused to dispose of the source files
+fn receive_sftp(
+    profile: &TransferProfile,
+    context: &TransferContext,
+) -> Result<(Vec<TransferredFile>, Option<Session>)> {
+    let protocol = profile.transfer_protocol.protocol.to_string();
+    info!(
+        "Attempting to receive file from SFTP source '{}'@{}:{}{} to local '{}'",
+        profile
+            .source
+            .authentication
+            .as_ref()
+            .map_or("unknown", |a| a.username.as_str()),
+        profile.source.host.as_deref().unwrap_or("localhost"),
+        profile.source.port.unwrap_or(22),
+        profile.source.path,
+        profile.destination.path
+    );
+    let session = connect_session_and_authenticate(
+        &protocol,
+        profile.source.authentication.as_ref(),
+        profile.source.host.as_deref(),
+        profile.source.port,
+        &context.connection_settings(profile),
+    )?;
+
+    let sftp = session.sftp()?;
+    let dst = PathBuf::from(&profile.destination.path);
+
+    match SourceLayout::resolve(profile, |path| is_remote_dir(&sftp, path))? {
+        SourceLayout::File(src) => {
+            wait_until_ready(profile, context, &src, |path| remote_file_state(&sftp, path))?;
+            let file = transfer_file_sftp(&session, &sftp, profile, context, &src, &dst, false)?; // download
+            Ok((vec![file], Some(session)))
+        }
+        SourceLayout::Tree {
+            root,
+            recursive,
+            matcher,
+        } => {
+            info!(
+                "Attempting to download remote directory '{}' (recursive: {}) to local path '{}'",
+                root.display(),
+                recursive,
+                dst.display()
+            );
+            let mut tree = walk_remote_dir(&sftp, &root, recursive)?.matching(matcher.as_ref());
+            retain_ready_files(profile, context, &root, &mut tree, |path| {
+                remote_file_state(&sftp, path)
+            })?;
+            let files = download_tree_sftp(&session, &sftp, profile, context, &root, &dst, &tree)?;
+            Ok((files, Some(session)))
+        }
+    }
+}
+
+// ローカルディレクトリ配下のファイルをリモートへミラーする
+// Mirror the files under a local directory to the remote destination
+fn upload_tree_sftp(
+    session: &Session,
+    sftp: &Sftp,
+    profile: &TransferProfile,
+    context: &TransferContext,
+    src: &Path,
+    dst: &Path,
+    tree: &DirTree,
+) -> Result<Vec<TransferredFile>> {
+    if tree.files.is_empty() {
+        warn!("No files to upload were found in '{}'", src.display());
+    }
+
+    ensure_remote_dir(sftp, dst)?;
+    for dir in &tree.dirs {
+        ensure_remote_dir(sftp, &dst.join(dir))?;
+    }
+    let mut transferred = Vec::with_capacity(tree.files.len());
+    for file in &tree.files {
+        let (src_file, dst_file) = (src.join(file), dst.join(file));
+        let mut transferred_file =
+            transfer_file_sftp(session, sftp, profile, context, &src_file, &dst_file, true)?;
+        transferred_file.relative = file.clone();
+        transferred.push(transferred_file);
     }
+
+    info!(
+        "Successfully uploaded {} file(s) from directory '{}' to '{}'",
+        tree.files.len(),
+        src.display(),
+        dst.display()
+    );
+    Ok(transferred)
 }
 
-fn transfer_file_sftp(sftp: &Sftp, src: &PathBuf, dst: &PathBuf, upload: bool) -> Result<()> {
+// リモートディレクトリ配下のファイルをローカルへミラーする
+// Mirror the files under a remote directory to the local destination
+fn download_tree_sftp(
+    session: &Session,
+    sftp: &Sftp,
+    profile: &TransferProfile,
+    context: &TransferContext,
+    src: &Path,
+    dst: &Path,
+    tree: &DirTree,
+) -> Result<Vec<TransferredFile>> {
+    if tree.files.is_empty() {
+        warn!("No files to download were found in remote directory '{}'", src.display());
+    }
+
+    fs::create_dir_all(dst)
+        .with_context(|| format!("Failed to create local directory: '{}'", dst.display()))?;
+    for dir in &tree.dirs {
+        let local_dir = dst.join(dir);
+        fs::create_dir_all(&local_dir).with_context(|| {
+            format!("Failed to create local directory: '{}'", local_dir.display())
+        })?;
+    }
+    let mut transferred = Vec::with_capacity(tree.files.len());
+    for file in &tree.files {
+        let (src_file, dst_file) = (src.join(file), dst.join(file));
+        let mut transferred_file =
+            transfer_file_sftp(session, sftp, profile, context, &src_file, &dst_file, false)?;
+        transferred_file.relative = file.clone();
+        transferred.push(transferred_file);
+    }
 
-    let max_mb = get_max_file_size_mb();
+    info!(
+        "Successfully downloaded {} file(s) from remote directory '{}' to '{}'",
+        tree.files.len(),
+        src.display(),
+        dst.display()
+    );
+    Ok(transferred)
+}
+
+fn transfer_file_sftp(
+    session: &Session,
+    sftp: &Sftp,
+    profile: &TransferProfile,
+    context: &TransferContext,
+    src: &Path,
+    dst: &Path,
+    upload: bool,
+) -> Result<TransferredFile> {
+    context.ensure_not_cancelled()?;
+    let resume = profile.resume.unwrap_or(false);
     if upload {
         info!(
             "Attempting to upload file from '{}' to remote path '{}'",
@@ -95,40 +248,121 @@ fn transfer_file_sftp(sftp: &Sftp, src: &PathBuf, dst: &PathBuf, upload: bool) -
 
         let metadata = local_file.metadata()?;
         let file_size = metadata.len();
-        let max_size_bytes = max_mb * 1024 * 1024;
-        if file_size > max_size_bytes {
-            return Err(anyhow!(
-                "File '{}' exceeds max allowed size ({} MB)",
-                src.display(),
-                max_size_bytes / 1024 / 1024
-            ));
+        context.ensure_size_allowed(profile, src, file_size)?;
+
+        // アトミック配信の場合は一時ファイルに書き込み、完了後にリネームする
+        // With atomic delivery, write to a temporary file and rename it once complete
+        let temp_path = profile.atomic.as_ref().map(|atomic| atomic.temp_path(dst));
+        let write_path = temp_path.as_deref().unwrap_or(dst);
+        if temp_path.is_some() {
+            info!("Writing to temporary remote file '{}'", write_path.display());
         }
 
-        let mut remote_file = sftp.create(Path::new(dst)).with_context(|| {
-            format!(
-                "Failed to create remote destination file for upload: '{}'",
-                dst.display()
-            )
-        })?;
-        copy(&mut local_file, &mut remote_file).with_context(|| {
-            format!(
-                "Failed to copy data during upload from '{}' to '{}'",
-                src.display(),
-                dst.display()
-            )
-        })?;
+        // 既存のリモートファイルがあれば、その続きから転送する（チェックポイントリスタート）
+        // If a remote file already exists, continue from where it left off (checkpoint restart)
+        let offset = match sftp.stat(write_path).ok().and_then(|stat| stat.size) {
+            Some(remote_size) if resume && remote_size > 0 => {
+                let mut remote_file = sftp.open(write_path).with_context(|| {
+                    format!(
+                        "Failed to open remote destination file for resume check: '{}'",
+                        write_path.display()
+                    )
+                })?;
+                resume_offset(&mut remote_file, remote_size, &mut local_file, file_size)?
+            }
+            _ => 0,
+        };
+
+        let mut remote_file = if offset > 0 {
+            let mut remote_file = sftp
+                .open_mode(write_path, OpenFlags::WRITE, 0o644, OpenType::File)
+                .with_context(|| {
+                    format!(
+                        "Failed to open remote destination file for resume: '{}'",
+                        write_path.display()
+                    )
+                })?;
+            remote_file.seek(SeekFrom::Start(offset))?;
+            remote_file
+        } else {
+            sftp.create(write_path).with_context(|| {
+                format!(
+                    "Failed to create remote destination file for upload: '{}'",
+                    write_path.display()
+                )
+            })?
+        };
+
+        let mut checksum = context.verify_method(profile).map(Checksum::new);
+        // 再開時も転送済み部分を含めたファイル全体のチェックサムを計算する。
+        // 転送済み部分は送信しないため、速度制限をかけずに先に読み込む
+        // When resuming, the checksum still covers the whole file including the part already sent.
+        // That part is not sent again, so it is read up front without throttling
+        if let Some(checksum) = checksum.as_mut().filter(|_| offset > 0) {
+            local_file.seek(SeekFrom::Start(0))?;
+            checksum.update_from_reader((&mut local_file).take(offset))?;
+        }
+        local_file.seek(SeekFrom::Start(offset))?;
+        let bytes = {
+            let mut reader = HashingReader::new(
+                KeepaliveReader::new(
+                    ProgressReader::new(
+                        ThrottledReader::new(
+                            CancellableReader::new(
+                                BufReader::with_capacity(context.options.buffer_size, &mut local_file),
+                                context,
+                            ),
+                            Throttle::for_profile(profile, context),
+                        ),
+                        context,
+                        src,
+                        offset,
+                        file_size,
+                    ),
+                    session,
+                ),
+                checksum.as_mut(),
+            );
+            copy(&mut reader, &mut remote_file).with_context(|| {
+                format!(
+                    "Failed to copy data during upload from '{}' to '{}'",
+                    src.display(),
+                    dst.display()
+                )
+            })?
+        };
+        drop(remote_file);
+
+        let checksum = match (context.verify_method(profile), checksum) {
+            (Some(method), Some(checksum)) => {
+                let local = checksum.finalize();
+                let remote = remote_checksum(session, Some(sftp), write_path, method)?;
+                ensure_checksums_match(write_path, method, &local, &remote)?;
+                Some(local)
+            }
+            _ => None,
+        };
+        if let Some(temp_path) = &temp_path {
+            commit_remote_file(session, Some(sftp), temp_path, dst)?;
+        }
         info!(
             "Successfully uploaded file from '{}' to '{}'",
             src.display(),
             dst.display()
         );
+        Ok(TransferredFile {
+            size: file_size,
+            bytes,
+            checksum,
+            ..TransferredFile::single(src, dst)
+        })
     } else {
         info!(
             "Attempting to download file from remote path '{}' to local path '{}'",
             src.display(),
             dst.display()
         );
-        let mut remote_file = sftp.open(Path::new(src)).with_context(|| {
+        let mut remote_file = sftp.open(src).with_context(|| {
             format!(
                 "Failed to open remote source file for download: '{}'",
                 src.display()
@@ -139,34 +373,115 @@ fn transfer_file_sftp(sftp: &Sftp, src: &PathBuf, dst: &PathBuf, upload: bool) -
         let file_size = stat
             .size
             .ok_or_else(|| anyhow!("Unable to get size of remote file"))?;
-        let max_size_bytes = max_mb * 1024 * 1024;
-        if file_size > max_size_bytes {
-            return Err(anyhow!(
-                "File '{}' exceeds max allowed size ({} MB)",
-                src.display(),
-                max_size_bytes / 1024 / 1024
-            ));
+        context.ensure_size_allowed(profile, src, file_size)?;
+
+        // アトミック配信の場合は転送先ディレクトリ内の一時ファイルに書き込む。
+        // 一時ファイルは失敗時に削除されるため、再開の対象にはならない。
+        // With atomic delivery, write to a temporary file in the destination directory.
+        // The temporary file is removed on failure, so it is never resumed.
+        let temp_file = profile
+            .atomic
+            .as_ref()
+            .map(|atomic| atomic.local_temp_file(dst))
+            .transpose()?;
+        if let Some(temp_file) = &temp_file {
+            if resume {
+                warn!("'resume' is ignored for atomic local downloads.");
+            }
+            info!(
+                "Writing to temporary local file '{}'",
+                temp_file.path().display()
+            );
         }
 
-        let mut local_file = File::create(dst).with_context(|| {
-            format!(
-                "Failed to create local destination file for download: '{}'",
-                dst.display()
-            )
-        })?;
-        copy(&mut remote_file, &mut local_file).with_context(|| {
-            format!(
-                "Failed to copy data during download from '{}' to '{}'",
-                src.display(),
-                dst.display()
-            )
-        })?;
+        // 既存のローカルファイルがあれば、その続きから転送する（チェックポイントリスタート）
+        // If a local file already exists, continue from where it left off (checkpoint restart)
+        let offset = match fs::metadata(dst).map(|m| m.len()) {
+            Ok(local_size) if resume && temp_file.is_none() && local_size > 0 => {
+                let mut local_file = File::open(dst)?;
+                resume_offset(&mut local_file, local_size, &mut remote_file, file_size)?
+            }
+            _ => 0,
+        };
+
+        let mut local_file = if let Some(temp_file) = &temp_file {
+            temp_file.as_file().try_clone()?
+        } else if offset > 0 {
+            OpenOptions::new().append(true).open(dst).with_context(|| {
+                format!(
+                    "Failed to open local destination file for resume: '{}'",
+                    dst.display()
+                )
+            })?
+        } else {
+            File::create(dst).with_context(|| {
+                format!(
+                    "Failed to create local destination file for download: '{}'",
+                    dst.display()
+                )
+            })?
+        };
+
+        let mut checksum = context.verify_method(profile).map(Checksum::new);
+        if let Some(checksum) = checksum.as_mut().filter(|_| offset > 0) {
+            // 再開時はローカルに残っている転送済み部分をチェックサムに含める
+            // When resuming, include the part already present locally in the checksum
+            checksum.update_from_reader(File::open(dst)?.take(offset))?;
+        }
+        remote_file.seek(SeekFrom::Start(offset))?;
+        let bytes = {
+            let mut reader = HashingReader::new(
+                KeepaliveReader::new(
+                    ProgressReader::new(
+                        ThrottledReader::new(
+                            CancellableReader::new(
+                                BufReader::with_capacity(context.options.buffer_size, &mut remote_file),
+                                context,
+                            ),
+                            Throttle::for_profile(profile, context),
+                        ),
+                        context,
+                        src,
+                        offset,
+                        file_size,
+                    ),
+                    session,
+                ),
+                checksum.as_mut(),
+            );
+            copy(&mut reader, &mut local_file).with_context(|| {
+                format!(
+                    "Failed to copy data during download from '{}' to '{}'",
+                    src.display(),
+                    dst.display()
+                )
+            })?
+        };
+        drop(remote_file);
+
+        let checksum = match (context.verify_method(profile), checksum) {
+            (Some(method), Some(checksum)) => {
+                let local = checksum.finalize();
+                let remote = remote_checksum(session, Some(sftp), src, method)?;
+                ensure_checksums_match(src, method, &local, &remote)?;
+                Some(local)
+            }
+            _ => None,
+        };
+        if let Some(temp_file) = temp_file {
+            drop(local_file);
+            commit_local_file(temp_file, dst)?;
+        }
         info!(
             "Successfully downloaded file from '{}' to '{}'",
             src.display(),
             dst.display()
         );
+        Ok(TransferredFile {
+            size: file_size,
+            bytes,
+            checksum,
+            ..TransferredFile::single(src, dst)
+        })
     }
-
-    Ok(())
 }
@@ -0,0 +1,264 @@
+use crate::{AppError, DirTree, Stability, TransferContext, TransferProfile};
+use anyhow::{Context, Result};
+use log::info;
+use ssh2::Sftp;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+/// 既定の安定待ちの上限（秒）
+/// Default cap on the wait for files to settle (seconds)
+pub const DEFAULT_STABILITY_MAX_WAIT_SECS: u64 = 300;
+// サイズと更新日時を確認し直す間隔の上限
+// Upper bound on the interval between checks of the size and mtime
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+impl Stability {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self
+            .trigger_file_suffix
+            .as_ref()
+            .is_some_and(|suffix| suffix.is_empty())
+        {
+            return Err(AppError::Validation(
+                "stability.triggerFileSuffix must not be empty".to_string(),
+            ));
+        }
+        // 上限より長い安定時間は満たされることがなく、毎回タイムアウトする
+        // A stable time longer than the cap can never be met and times out on every run
+        if let Some(stable_for) = self.stable_for()
+            && self.max_wait() < stable_for
+        {
+            return Err(AppError::Validation(format!(
+                "stability.maxWaitSecs ({}) must not be less than stability.stableSecs ({})",
+                self.max_wait().as_secs(),
+                stable_for.as_secs()
+            )));
+        }
+        Ok(())
+    }
+
+    /// サイズと更新日時が変わらないまま経過する必要がある時間（確認しない場合はNone）
+    /// How long the size and mtime must stay unchanged (None when not checked)
+    pub fn stable_for(&self) -> Option<Duration> {
+        self.stable_secs
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+
+    pub fn max_wait(&self) -> Duration {
+        Duration::from_secs(
+            self.max_wait_secs
+                .unwrap_or(DEFAULT_STABILITY_MAX_WAIT_SECS),
+        )
+    }
+
+    /// ファイルに対応するトリガーファイルのパス（例: report.csv → report.csv.ok）
+    /// Path of the trigger file that goes with a file (e.g. report.csv → report.csv.ok)
+    pub fn trigger_file(&self, path: &Path) -> Option<PathBuf> {
+        let suffix = self.trigger_file_suffix.as_ref()?;
+        let mut trigger = path.as_os_str().to_owned();
+        trigger.push(suffix);
+        Some(PathBuf::from(trigger))
+    }
+
+    /// トリガーファイル自体かどうか（トリガーファイルは転送しない）
+    /// Whether a path is a trigger file itself (trigger files are not transferred)
+    pub fn is_trigger_file(&self, path: &Path) -> bool {
+        self.trigger_file_suffix.as_ref().is_some_and(|suffix| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with(suffix.as_str()))
+        })
+    }
+
+    pub fn deletes_trigger_file(&self) -> bool {
+        self.trigger_file_suffix.is_some() && self.delete_trigger_file.unwrap_or(false)
+    }
+}
+
+/// 安定性の判定に使うファイルの状態
+/// State of a file used to judge whether it is stable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileState {
+    pub size: u64,
+    // 更新日時（UNIXエポックからの秒数、取得できない場合はNone）
+    // Modification time (seconds since the UNIX epoch, None when unavailable)
+    pub mtime: Option<u64>,
+}
+
+pub fn local_file_state(path: &Path) -> Result<FileState> {
+    let metadata = fs::metadata(path)
+        .with_context(|| format!("Failed to read metadata of '{}'", path.display()))?;
+    Ok(FileState {
+        size: metadata.len(),
+        mtime: metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|mtime| mtime.as_secs()),
+    })
+}
+
+pub fn remote_file_state(sftp: &Sftp, path: &Path) -> Result<FileState> {
+    let stat = sftp
+        .stat(path)
+        .with_context(|| format!("Failed to stat remote file '{}'", path.display()))?;
+    Ok(FileState {
+        size: stat.size.unwrap_or(0),
+        mtime: stat.mtime,
+    })
+}
+
+/// 単一ファイルの転送元が受け取り可能になるまで待つ。
+/// `source.stability` が未指定なら何もせず、受け取り可能にならなければ `AppError::SourceNotReady` を返す。
+/// Wait until a single source file can be picked up.
+/// Does nothing without `source.stability`; fails with `AppError::SourceNotReady` when it never becomes ready.
+pub fn wait_until_ready<S>(
+    profile: &TransferProfile,
+    context: &TransferContext,
+    path: &Path,
+    stat: S,
+) -> Result<()>
+where
+    S: Fn(&Path) -> Result<FileState>,
+{
+    let Some(stability) = &profile.source.stability else {
+        return Ok(());
+    };
+    let results = check_files(stability, context, vec![path.to_path_buf()], stat)?;
+    match results.into_iter().next() {
+        Some((_, Some(reason))) => Err(AppError::SourceNotReady {
+            path: path.display().to_string(),
+            reason,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// ツリーから、トリガーファイルと受け取り可能でないファイルを除く（後者は次回の実行に回す）
+/// Drop the trigger files and the files that cannot be picked up yet from a tree (the latter are left for the next run)
+pub fn retain_ready_files<S>(
+    profile: &TransferProfile,
+    context: &TransferContext,
+    root: &Path,
+    tree: &mut DirTree,
+    stat: S,
+) -> Result<()>
+where
+    S: Fn(&Path) -> Result<FileState>,
+{
+    let Some(stability) = &profile.source.stability else {
+        return Ok(());
+    };
+    tree.retain_files(|file| !stability.is_trigger_file(file));
+    let paths = tree.files.iter().map(|file| root.join(file)).collect();
+    let results = check_files(stability, context, paths, stat)?;
+    let mut skipped = Vec::new();
+    for (path, reason) in results {
+        if let Some(reason) = reason {
+            info!("Skipping '{}' for now: {}", path.display(), reason);
+            skipped.push(path);
+        }
+    }
+    tree.retain_files(|file| !skipped.contains(&root.join(file)));
+    Ok(())
+}
+
+// 各ファイルが受け取り可能かどうかを判定する（受け取れない場合はその理由）
+// Decide for each file whether it can be picked up (with the reason when it cannot)
+fn check_files<S>(
+    stability: &Stability,
+    context: &TransferContext,
+    paths: Vec<PathBuf>,
+    stat: S,
+) -> Result<Vec<(PathBuf, Option<String>)>>
+where
+    S: Fn(&Path) -> Result<FileState>,
+{
+    let mut reasons: Vec<Option<String>> = vec![None; paths.len()];
+    let mut watching = Vec::new();
+    for (index, path) in paths.iter().enumerate() {
+        if let Some(trigger) = stability.trigger_file(path)
+            && stat(&trigger).is_err()
+        {
+            reasons[index] = Some(format!("trigger file '{}' not found", trigger.display()));
+            continue;
+        }
+        match stat(path) {
+            Ok(state) => watching.push(Observed {
+                index,
+                state,
+                since: Instant::now(),
+                stable: false,
+            }),
+            Err(e) => reasons[index] = Some(format!("{:#}", e)),
+        }
+    }
+
+    if let Some(stable_for) = stability.stable_for()
+        && !watching.is_empty()
+    {
+        info!(
+            "Waiting for {} file(s) to stay unchanged for {}s (up to {}s)...",
+            watching.len(),
+            stable_for.as_secs(),
+            stability.max_wait().as_secs()
+        );
+        let started = Instant::now();
+        let poll_interval = stable_for.min(MAX_POLL_INTERVAL);
+        loop {
+            if watching.iter().all(|observed| observed.stable)
+                || started.elapsed() >= stability.max_wait()
+            {
+                break;
+            }
+            thread::sleep(poll_interval);
+            context.ensure_not_cancelled()?;
+            watching.retain_mut(|observed| {
+                if observed.stable {
+                    return true;
+                }
+                match stat(&paths[observed.index]) {
+                    Ok(current) if current == observed.state => {
+                        observed.stable = observed.since.elapsed() >= stable_for;
+                        true
+                    }
+                    Ok(current) => {
+                        observed.state = current;
+                        observed.since = Instant::now();
+                        true
+                    }
+                    Err(e) => {
+                        reasons[observed.index] = Some(format!("{:#}", e));
+                        false
+                    }
+                }
+            });
+        }
+        for observed in watching.iter().filter(|observed| !observed.stable) {
+            reasons[observed.index] = Some(format!(
+                "still being written after waiting {}s",
+                started.elapsed().as_secs()
+            ));
+        }
+    }
+
+    Ok(paths.into_iter().zip(reasons).collect())
+}
+
+// 安定するまで確認を続けるファイル
+// A file watched until it settles
+struct Observed {
+    index: usize,
+    state: FileState,
+    // 最後の状態になった時刻
+    // When the last state was first seen
+    since: Instant,
+    // 再確認で変化がないまま stableSecs が経過したことを確かめたかどうか
+    // Whether a recheck confirmed it unchanged for stableSecs
+    stable: bool,
+}
@@ -0,0 +1,189 @@
+use crate::{BandwidthLimit, TransferContext, TransferProfile};
+use chrono::Local;
+use std::{
+    io::{self, Read},
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+// バケットに貯められる量（この時間分の転送量まで一度に読み込める）
+// Capacity of the bucket (up to this much time's worth of data can be read at once)
+const BURST: Duration = Duration::from_millis(250);
+// 時間帯ごとの上限を評価し直す間隔
+// Interval for re-evaluating time-of-day limits
+const RATE_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// トークンバケット方式で転送速度を制限する
+/// Limits the transfer rate with a token bucket
+#[derive(Debug)]
+pub struct Throttle {
+    limits: Vec<BandwidthLimit>,
+    rate: Option<u64>,
+    rate_checked: Option<Instant>,
+    tokens: f64,
+    last_refill: Instant,
+    // 他の転送と共有するバケット（このバケットとあわせて両方の上限を守る）
+    // Bucket shared with other transfers (both this bucket's and its limit are honored)
+    shared: Option<SharedThrottle>,
+}
+
+impl Throttle {
+    /// 複数の上限のうち、その時点で最も小さいものを適用する
+    /// The lowest of the limits in effect at the time applies
+    pub fn new(limits: Vec<BandwidthLimit>) -> Self {
+        Throttle {
+            limits,
+            rate: None,
+            rate_checked: None,
+            tokens: 0.0,
+            last_refill: Instant::now(),
+            shared: None,
+        }
+    }
+
+    /// 他の転送と共有するバケットからも読み込み量を差し引く
+    /// Also draw the bytes read from a bucket shared with other transfers
+    pub fn with_shared(mut self, shared: Option<SharedThrottle>) -> Self {
+        self.shared = shared;
+        self
+    }
+
+    /// プロファイルの上限と、すべての転送で共有するconfig.yamlの上限から作成する
+    /// Create from the profile's limit and the config.yaml cap shared by every transfer
+    pub fn for_profile(profile: &TransferProfile, context: &TransferContext) -> Self {
+        Throttle::new(profile.bandwidth_limit.iter().cloned().collect())
+            .with_shared(context.global_throttle.clone())
+    }
+
+    /// 現在の上限（バイト/秒、Noneは無制限）
+    /// The limit currently in effect (bytes per second, None for unlimited)
+    pub fn rate(&mut self) -> Option<u64> {
+        self.refresh_rate(Instant::now());
+        let shared = self.shared.as_ref().and_then(|shared| shared.lock().rate());
+        match (self.rate, shared) {
+            (Some(own), Some(shared)) => Some(own.min(shared)),
+            (own, shared) => own.or(shared),
+        }
+    }
+
+    fn refresh_rate(&mut self, now: Instant) {
+        if self
+            .rate_checked
+            .is_some_and(|checked| now.duration_since(checked) < RATE_REFRESH_INTERVAL)
+        {
+            return;
+        }
+        let time = Local::now().time();
+        let rate = self
+            .limits
+            .iter()
+            .filter_map(|limit| limit.bytes_per_sec_at(time))
+            .min();
+        if rate != self.rate {
+            // 上限が変わったら、新しい上限の1回分からやり直す
+            // When the limit changes, start over with one burst of the new limit
+            self.rate = rate;
+            self.tokens = rate.map(capacity).unwrap_or(0.0);
+            self.last_refill = now;
+        }
+        self.rate_checked = Some(now);
+    }
+
+    /// 1回の読み込みで要求してよいバイト数
+    /// Number of bytes a single read may request
+    pub fn max_chunk(&mut self, requested: usize) -> usize {
+        let requested = match &self.shared {
+            Some(shared) => shared.lock().max_chunk(requested),
+            None => requested,
+        };
+        self.refresh_rate(Instant::now());
+        match self.rate {
+            Some(rate) => requested.min(capacity(rate) as usize),
+            None => requested,
+        }
+    }
+
+    /// 転送したバイト数を差し引き、上限を守るために待つべき時間を返す
+    /// Deduct the bytes transferred and return how long to wait to stay within the limit
+    pub fn consume(&mut self, bytes: usize) -> Duration {
+        let shared = self
+            .shared
+            .as_ref()
+            .map_or(Duration::ZERO, |shared| shared.lock().consume(bytes));
+        shared.max(self.consume_own(bytes))
+    }
+
+    fn consume_own(&mut self, bytes: usize) -> Duration {
+        let now = Instant::now();
+        self.refresh_rate(now);
+        let Some(rate) = self.rate else {
+            return Duration::ZERO;
+        };
+        let refill = now.duration_since(self.last_refill).as_secs_f64() * rate as f64;
+        self.tokens = (self.tokens + refill).min(capacity(rate));
+        self.last_refill = now;
+
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(-self.tokens / rate as f64)
+    }
+}
+
+/// 複数の転送で共有するトークンバケット（config.yamlの全体の上限）。
+/// 同時に実行される転送の読み込み量をすべて差し引くため、合計の速度がこの上限に収まる。
+/// Token bucket shared by several transfers (the global cap in config.yaml).
+/// Every concurrent transfer draws from it, so their combined rate stays within the cap.
+#[derive(Debug, Clone)]
+pub struct SharedThrottle {
+    limit: BandwidthLimit,
+    bucket: Arc<Mutex<Throttle>>,
+}
+
+impl SharedThrottle {
+    pub fn new(limit: BandwidthLimit) -> Self {
+        SharedThrottle {
+            bucket: Arc::new(Mutex::new(Throttle::new(vec![limit.clone()]))),
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> &BandwidthLimit {
+        &self.limit
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Throttle> {
+        self.bucket.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn capacity(rate: u64) -> f64 {
+    (rate as f64 * BURST.as_secs_f64()).max(1.0)
+}
+
+/// 読み込みのたびに `Throttle` で速度を制限するリーダー
+/// Reader that limits its rate through a `Throttle` on every read
+pub struct ThrottledReader<R> {
+    inner: R,
+    throttle: Throttle,
+}
+
+impl<R: Read> ThrottledReader<R> {
+    pub fn new(inner: R, throttle: Throttle) -> Self {
+        ThrottledReader { inner, throttle }
+    }
+}
+
+impl<R: Read> Read for ThrottledReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.throttle.max_chunk(buf.len());
+        let n = self.inner.read(&mut buf[..len])?;
+        let wait = self.throttle.consume(n);
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+        Ok(n)
+    }
+}
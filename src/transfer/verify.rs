@@ -0,0 +1,162 @@
+use crate::{exec_remote, shell_quote, AppError, VerifyMethod};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use ssh2::{Session, Sftp};
+use std::{
+    io::{self, Read},
+    path::Path,
+};
+
+/// 転送中に計算するチェックサム
+/// Checksum computed while streaming
+pub enum Checksum {
+    Sha256(Sha256),
+    Md5(Md5),
+    Size(u64),
+}
+
+impl Checksum {
+    pub fn new(method: &VerifyMethod) -> Self {
+        match method {
+            VerifyMethod::Sha256 => Checksum::Sha256(Sha256::new()),
+            VerifyMethod::Md5 => Checksum::Md5(Md5::new()),
+            VerifyMethod::Size => Checksum::Size(0),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Checksum::Sha256(hasher) => hasher.update(data),
+            Checksum::Md5(hasher) => hasher.update(data),
+            Checksum::Size(size) => *size += data.len() as u64,
+        }
+    }
+
+    /// readerの内容をすべて読み込んでチェックサムに反映する
+    /// Feed everything from the reader into the checksum
+    pub fn update_from_reader<R: Read>(&mut self, reader: R) -> io::Result<u64> {
+        let mut hashing = HashingReader::new(reader, Some(&mut *self));
+        io::copy(&mut hashing, &mut io::sink())
+    }
+
+    /// 16進文字列（sizeの場合はバイト数）を返す
+    /// Returns the lowercase hex digest (or the byte count for `size`)
+    pub fn finalize(self) -> String {
+        match self {
+            Checksum::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Checksum::Md5(hasher) => format!("{:x}", hasher.finalize()),
+            Checksum::Size(size) => size.to_string(),
+        }
+    }
+}
+
+/// 読み込んだデータをチェックサムに反映しながら読み進めるReader
+/// Reader that feeds every byte it reads into a checksum
+pub struct HashingReader<'a, R> {
+    inner: R,
+    checksum: Option<&'a mut Checksum>,
+}
+
+impl<'a, R: Read> HashingReader<'a, R> {
+    pub fn new(inner: R, checksum: Option<&'a mut Checksum>) -> Self {
+        HashingReader { inner, checksum }
+    }
+}
+
+impl<R: Read> Read for HashingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(checksum) = self.checksum.as_deref_mut() {
+            checksum.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// リモートファイルのチェックサムを取得する。
+/// まず同じセッション上で `sha256sum` / `md5sum` を実行し、
+/// 利用できない場合はSFTPでファイルを読み直して計算する。
+/// Get the checksum of a remote file.
+/// Runs `sha256sum` / `md5sum` over the same session first and falls back to
+/// re-reading the file over SFTP when the command is not available.
+pub fn remote_checksum(
+    session: &Session,
+    sftp: Option<&Sftp>,
+    path: &Path,
+    method: &VerifyMethod,
+) -> Result<String> {
+    if let VerifyMethod::Size = method {
+        if let Some(sftp) = sftp {
+            let stat = sftp.stat(path).with_context(|| {
+                format!("Failed to stat remote file for verification: '{}'", path.display())
+            })?;
+            return stat
+                .size
+                .map(|size| size.to_string())
+                .ok_or_else(|| anyhow!("Unable to get size of remote file '{}'", path.display()));
+        }
+        let output = exec_remote(session, &format!("wc -c < {}", shell_quote(path)))?;
+        return Ok(output.trim().to_string());
+    }
+
+    let command = match method {
+        VerifyMethod::Sha256 => "sha256sum",
+        VerifyMethod::Md5 => "md5sum",
+        VerifyMethod::Size => unreachable!(),
+    };
+    match exec_remote(session, &format!("{} {}", command, shell_quote(path))) {
+        Ok(output) => {
+            if let Some(digest) = output.split_whitespace().next() {
+                return Ok(digest.to_lowercase());
+            }
+            warn!("'{}' returned no output for '{}'.", command, path.display());
+        }
+        Err(e) => debug!("Remote '{}' failed for '{}': {:?}", command, path.display(), e),
+    }
+
+    let sftp = sftp.ok_or_else(|| {
+        anyhow!(
+            "Unable to compute remote checksum of '{}': '{}' is not available and SFTP is not supported",
+            path.display(),
+            command
+        )
+    })?;
+    info!(
+        "Remote '{}' is not available. Re-reading '{}' over SFTP for verification.",
+        command,
+        path.display()
+    );
+    let remote_file = sftp.open(path).with_context(|| {
+        format!("Failed to open remote file for verification: '{}'", path.display())
+    })?;
+    let mut checksum = Checksum::new(method);
+    checksum.update_from_reader(remote_file)?;
+    Ok(checksum.finalize())
+}
+
+/// ローカル側とリモート側のチェックサムを比較する
+/// Compare the local and remote checksums
+pub fn ensure_checksums_match(
+    path: &Path,
+    method: &VerifyMethod,
+    local: &str,
+    remote: &str,
+) -> Result<(), AppError> {
+    if local != remote {
+        return Err(AppError::ChecksumMismatch {
+            path: path.display().to_string(),
+            method: method.to_string(),
+            local: local.to_string(),
+            remote: remote.to_string(),
+        });
+    }
+    info!(
+        "Verified '{}' with {} ({}).",
+        path.display(),
+        method,
+        local
+    );
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use crate::{AppError, FileMatcher, FileSelection, TransferProfile};
+use anyhow::{Context, Result};
+use log::debug;
+use ssh2::Sftp;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// ディレクトリツリーを走査した結果（ルートからの相対パス）
+/// Result of walking a directory tree (paths are relative to the root)
+#[derive(Debug, Default)]
+pub struct DirTree {
+    pub dirs: Vec<PathBuf>,
+    pub files: Vec<PathBuf>,
+}
+
+impl DirTree {
+    /// マッチしたファイルと、それらを含むディレクトリだけを残す
+    /// Keep only the matching files and the directories that contain them
+    pub fn retain_matching(&mut self, matcher: &FileMatcher) {
+        self.retain_files(|file| matcher.is_match(file));
+    }
+
+    /// 条件を満たすファイルと、それらを含むディレクトリだけを残す
+    /// Keep only the files for which `keep` returns true and the directories that contain them
+    pub fn retain_files(&mut self, keep: impl FnMut(&PathBuf) -> bool) {
+        self.files.retain(keep);
+        let files = &self.files;
+        self.dirs
+            .retain(|dir| files.iter().any(|file| file.starts_with(dir)));
+    }
+
+    /// `matcher` が指定されていれば、マッチしたものだけを残したツリーを返す
+    /// The tree reduced to the matching entries when a `matcher` is given
+    pub fn matching(mut self, matcher: Option<&FileMatcher>) -> Self {
+        if let Some(matcher) = matcher {
+            self.retain_matching(matcher);
+        }
+        self
+    }
+}
+
+/// 転送元の構成（単一ファイル、またはディレクトリ配下のファイル群）
+/// Layout of the transfer source (a single file, or files under a directory)
+#[derive(Debug)]
+pub enum SourceLayout {
+    File(PathBuf),
+    Tree {
+        root: PathBuf,
+        recursive: bool,
+        matcher: Option<FileMatcher>,
+    },
+}
+
+impl SourceLayout {
+    /// `is_dir` は転送元（ローカルまたはリモート）がディレクトリかどうかを判定する
+    /// `is_dir` tells whether a path on the source side (local or remote) is a directory
+    pub fn resolve(profile: &TransferProfile, is_dir: impl Fn(&Path) -> bool) -> Result<Self> {
+        let recursive = profile.recursive.unwrap_or(false);
+
+        if let Some(selection) = FileSelection::from_source(&profile.source)? {
+            return Ok(SourceLayout::Tree {
+                root: selection.root,
+                recursive,
+                matcher: Some(selection.matcher),
+            });
+        }
+
+        let src = PathBuf::from(&profile.source.path);
+        if is_dir(&src) {
+            if !recursive {
+                return Err(AppError::Validation(format!(
+                    "Source '{}' is a directory. Set 'recursive: true' to transfer directories",
+                    src.display()
+                ))
+                .into());
+            }
+            return Ok(SourceLayout::Tree {
+                root: src,
+                recursive,
+                matcher: None,
+            });
+        }
+
+        Ok(SourceLayout::File(src))
+    }
+}
+
+/// ローカルディレクトリを走査する（`recursive` がfalseの場合は直下のファイルのみ）
+/// Walk a local directory (only its direct files when `recursive` is false)
+pub fn walk_local_dir(root: &Path, recursive: bool) -> Result<DirTree> {
+    let mut tree = DirTree::default();
+    walk_local_inner(root, Path::new(""), recursive, &mut tree)?;
+    Ok(tree)
+}
+
+fn walk_local_inner(root: &Path, relative: &Path, recursive: bool, tree: &mut DirTree) -> Result<()> {
+    let dir = root.join(relative);
+    let entries = fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read local directory: '{}'", dir.display()))?;
+
+    for entry in entries {
+        let entry = entry?;
+        let rel_path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            if recursive {
+                tree.dirs.push(rel_path.clone());
+                walk_local_inner(root, &rel_path, recursive, tree)?;
+            }
+        } else {
+            tree.files.push(rel_path);
+        }
+    }
+    Ok(())
+}
+
+/// リモートディレクトリをSFTP経由で走査する（`recursive` がfalseの場合は直下のファイルのみ）
+/// Walk a remote directory over SFTP (only its direct files when `recursive` is false)
+pub fn walk_remote_dir(sftp: &Sftp, root: &Path, recursive: bool) -> Result<DirTree> {
+    let mut tree = DirTree::default();
+    walk_remote_inner(sftp, root, Path::new(""), recursive, &mut tree)?;
+    Ok(tree)
+}
+
+fn walk_remote_inner(
+    sftp: &Sftp,
+    root: &Path,
+    relative: &Path,
+    recursive: bool,
+    tree: &mut DirTree,
+) -> Result<()> {
+    let dir = root.join(relative);
+    let entries = sftp
+        .readdir(&dir)
+        .with_context(|| format!("Failed to read remote directory: '{}'", dir.display()))?;
+
+    for (path, stat) in entries {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        let rel_path = relative.join(name);
+        if stat.is_dir() {
+            if recursive {
+                tree.dirs.push(rel_path.clone());
+                walk_remote_inner(sftp, root, &rel_path, recursive, tree)?;
+            }
+        } else {
+            tree.files.push(rel_path);
+        }
+    }
+    Ok(())
+}
+
+/// リモートパスがディレクトリかどうかを判定する（存在しない場合はfalse）
+/// Check whether a remote path is a directory (false if it does not exist)
+pub fn is_remote_dir(sftp: &Sftp, path: &Path) -> bool {
+    sftp.stat(path).map(|stat| stat.is_dir()).unwrap_or(false)
+}
+
+/// リモートディレクトリが存在しなければ作成する
+/// Create the remote directory if it does not exist yet
+pub fn ensure_remote_dir(sftp: &Sftp, dir: &Path) -> Result<()> {
+    if is_remote_dir(sftp, dir) {
+        return Ok(());
+    }
+    debug!("Creating remote directory: '{}'", dir.display());
+    sftp.mkdir(dir, 0o755)
+        .with_context(|| format!("Failed to create remote directory: '{}'", dir.display()))?;
+    Ok(())
+}
@@ -0,0 +1,238 @@
+use crate::{AppError, HostKeyPolicy, HostKeySettings};
+use anyhow::{Context, Result};
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    Engine,
+};
+use dirs::home_dir;
+use log::{debug, info, warn};
+use sha2::{Digest, Sha256};
+use ssh2::{CheckResult, HostKeyType, KnownHostFileKind, Session};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// OpenSSH形式のSHA256フィンガープリント（`SHA256:` + パディングなしBase64）を返す
+/// OpenSSH style SHA256 fingerprint (`SHA256:` + unpadded Base64)
+pub fn host_key_fingerprint(key: &[u8]) -> String {
+    format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(key)))
+}
+
+/// フィンガープリントを比較用に正規化する（`SHA256:` 接頭辞と末尾の `=` は任意）
+/// Normalise a fingerprint for comparison (the `SHA256:` prefix and trailing `=` are optional)
+fn normalize_fingerprint(fingerprint: &str) -> &str {
+    let trimmed = fingerprint.trim();
+    let without_prefix = trimmed
+        .strip_prefix("SHA256:")
+        .or_else(|| trimmed.strip_prefix("sha256:"))
+        .unwrap_or(trimmed);
+    without_prefix.trim_end_matches('=')
+}
+
+pub fn fingerprints_match(expected: &str, actual: &str) -> bool {
+    normalize_fingerprint(expected) == normalize_fingerprint(actual)
+}
+
+/// known_hostsに記録するホスト名（22番以外のポートは `[host]:port`）
+/// Host name as recorded in known_hosts (`[host]:port` for ports other than 22)
+pub fn known_hosts_entry_name(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+fn key_type_name(key_type: HostKeyType) -> Option<&'static str> {
+    match key_type {
+        HostKeyType::Rsa => Some("ssh-rsa"),
+        HostKeyType::Dss => Some("ssh-dss"),
+        HostKeyType::Ecdsa256 => Some("ecdsa-sha2-nistp256"),
+        HostKeyType::Ecdsa384 => Some("ecdsa-sha2-nistp384"),
+        HostKeyType::Ecdsa521 => Some("ecdsa-sha2-nistp521"),
+        HostKeyType::Ed25519 => Some("ssh-ed25519"),
+        HostKeyType::Unknown => None,
+    }
+}
+
+impl HostKeySettings {
+    pub fn policy(&self) -> HostKeyPolicy {
+        self.policy.clone().unwrap_or_default()
+    }
+
+    /// 使用するknown_hostsファイルのパス
+    /// Path of the known_hosts file to use
+    pub fn known_hosts_path(&self) -> Result<PathBuf> {
+        match &self.known_hosts_file {
+            Some(path) => Ok(PathBuf::from(shellexpand::tilde(path).as_ref())),
+            None => Ok(home_dir()
+                .context("No home dir found for resolving ~/.ssh/known_hosts")?
+                .join(".ssh")
+                .join("known_hosts")),
+        }
+    }
+}
+
+/// ハンドシェイク後のサーバーのホスト鍵を検証する。
+/// `pinned` が指定されている場合はknown_hostsの代わりにフィンガープリントと照合する。
+/// Verify the server's host key after the handshake.
+/// When `pinned` is given, the key is checked against that fingerprint instead of known_hosts.
+pub fn verify_host_key(
+    session: &Session,
+    host: &str,
+    port: u16,
+    settings: &HostKeySettings,
+    pinned: Option<&str>,
+) -> Result<()> {
+    let (key, key_type) = session
+        .host_key()
+        .ok_or_else(|| AppError::HostKeyVerification {
+            host: known_hosts_entry_name(host, port),
+            reason: "the server did not provide a host key".to_string(),
+        })?;
+    let fingerprint = host_key_fingerprint(key);
+    info!(
+        "Host key for {}:{} ({}): {}",
+        host,
+        port,
+        key_type_name(key_type).unwrap_or("unknown"),
+        fingerprint
+    );
+
+    if let Some(pinned) = pinned {
+        if !fingerprints_match(pinned, &fingerprint) {
+            return Err(AppError::HostKeyVerification {
+                host: known_hosts_entry_name(host, port),
+                reason: format!(
+                    "fingerprint {} does not match the pinned fingerprint {}",
+                    fingerprint, pinned
+                ),
+            }
+            .into());
+        }
+        info!("Host key matches the pinned fingerprint.");
+        return Ok(());
+    }
+
+    let policy = settings.policy();
+    if policy == HostKeyPolicy::Off {
+        warn!(
+            "Host key verification is disabled. Accepting {} for {}:{} without checking.",
+            fingerprint, host, port
+        );
+        return Ok(());
+    }
+
+    let path = settings.known_hosts_path()?;
+    let mut known_hosts = session.known_hosts()?;
+    load_known_hosts(&mut known_hosts, &path)?;
+
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => {
+            info!("Host key found in '{}'.", path.display());
+            Ok(())
+        }
+        CheckResult::Mismatch => Err(AppError::HostKeyVerification {
+            host: known_hosts_entry_name(host, port),
+            reason: format!(
+                "host key {} does not match the key recorded in '{}'. The host key may have changed or the connection may be intercepted",
+                fingerprint,
+                path.display()
+            ),
+        }
+        .into()),
+        CheckResult::NotFound if policy == HostKeyPolicy::AcceptNew => {
+            append_known_host(&path, host, port, key, key_type)?;
+            warn!(
+                "Added new host key {} for {}:{} to '{}'.",
+                fingerprint,
+                host,
+                port,
+                path.display()
+            );
+            Ok(())
+        }
+        CheckResult::NotFound => Err(AppError::HostKeyVerification {
+            host: known_hosts_entry_name(host, port),
+            reason: format!(
+                "host is not listed in '{}' (fingerprint {}). Add it to known_hosts, pin 'hostKeyFingerprint' or use the 'accept-new' policy",
+                path.display(),
+                fingerprint
+            ),
+        }
+        .into()),
+        CheckResult::Failure => Err(AppError::HostKeyVerification {
+            host: known_hosts_entry_name(host, port),
+            reason: format!("failed to check the host key against '{}'", path.display()),
+        }
+        .into()),
+    }
+}
+
+// known_hostsを1行ずつ読み込む。libssh2が解釈できない行（@cert-authorityなど）は読み飛ばす
+// Load known_hosts line by line, skipping lines libssh2 cannot parse (e.g. @cert-authority)
+pub fn load_known_hosts(known_hosts: &mut ssh2::KnownHosts, path: &Path) -> Result<()> {
+    if !path.exists() {
+        debug!("Known hosts file '{}' does not exist.", path.display());
+        return Ok(());
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read known hosts file: '{}'", path.display()))?;
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Err(e) = known_hosts.read_str(line, KnownHostFileKind::OpenSSH) {
+            debug!(
+                "Skipping line {} of '{}': {}",
+                index + 1,
+                path.display(),
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+// 既存の内容を書き換えないよう、新しいホスト鍵は末尾に追記する
+// Append the new host key so that existing entries are left untouched
+pub fn append_known_host(
+    path: &Path,
+    host: &str,
+    port: u16,
+    key: &[u8],
+    key_type: HostKeyType,
+) -> Result<()> {
+    let type_name = key_type_name(key_type).ok_or_else(|| AppError::HostKeyVerification {
+        host: known_hosts_entry_name(host, port),
+        reason: "the host key type is not supported".to_string(),
+    })?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create directory for known hosts: '{}'", parent.display())
+        })?;
+    }
+    // 既存ファイルが改行で終わっていない場合は改行を補う
+    // Add a newline first if the existing file does not end with one
+    let needs_newline = fs::read(path)
+        .map(|content| !content.is_empty() && !content.ends_with(b"\n"))
+        .unwrap_or(false);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open known hosts file: '{}'", path.display()))?;
+    writeln!(
+        file,
+        "{}{} {} {}",
+        if needs_newline { "\n" } else { "" },
+        known_hosts_entry_name(host, port),
+        type_name,
+        STANDARD.encode(key)
+    )
+    .with_context(|| format!("Failed to write known hosts file: '{}'", path.display()))?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use crate::{resolve_secret_ref, AppError, Authentication};
+use log::{debug, warn};
+use regex::Regex;
+use ssh2::{KeyboardInteractivePrompt, Prompt};
+
+/// keyboard-interactive認証のプロンプトに、設定された秘密情報で応答する
+/// Answers keyboard-interactive prompts with the configured secrets
+pub struct PromptResponder {
+    responses: Vec<(Regex, String)>,
+    // どのパターンにも一致しない場合の応答（passwordRef）
+    // Answer used when no pattern matches (passwordRef)
+    fallback: Option<String>,
+}
+
+impl PromptResponder {
+    pub fn new(responses: Vec<(Regex, String)>, fallback: Option<String>) -> Self {
+        PromptResponder {
+            responses,
+            fallback,
+        }
+    }
+
+    /// 認証設定の `prompts` と `passwordRef` から応答を組み立てる
+    /// Build the responder from the `prompts` and `passwordRef` of the authentication config
+    pub fn from_auth(auth: &Authentication) -> Result<Self, AppError> {
+        let mut responses = Vec::new();
+        for prompt in auth.prompts.as_deref().unwrap_or_default() {
+            let pattern = Regex::new(&prompt.pattern).map_err(|e| {
+                AppError::Validation(format!("Invalid prompt pattern '{}': {}", prompt.pattern, e))
+            })?;
+            responses.push((pattern, resolve_secret_ref(&prompt.secret_ref)?));
+        }
+        let fallback = auth
+            .password_ref
+            .as_deref()
+            .map(resolve_secret_ref)
+            .transpose()?;
+        Ok(PromptResponder::new(responses, fallback))
+    }
+
+    /// プロンプト文字列に対する応答を返す（最初に一致したパターンを使う）
+    /// Answer for a prompt text (the first matching pattern wins)
+    pub fn respond(&self, text: &str) -> Option<&str> {
+        self.responses
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(text))
+            .map(|(_, answer)| answer.as_str())
+            .or(self.fallback.as_deref())
+    }
+}
+
+impl KeyboardInteractivePrompt for PromptResponder {
+    fn prompt<'a>(
+        &mut self,
+        _username: &str,
+        instructions: &str,
+        prompts: &[Prompt<'a>],
+    ) -> Vec<String> {
+        if !instructions.is_empty() {
+            debug!("Keyboard-interactive instructions: {}", instructions);
+        }
+        prompts
+            .iter()
+            .map(|prompt| {
+                debug!("Keyboard-interactive prompt: '{}'", prompt.text);
+                self.respond(&prompt.text)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| {
+                        warn!(
+                            "No configured answer matches the prompt '{}'. Sending an empty response.",
+                            prompt.text
+                        );
+                        String::new()
+                    })
+            })
+            .collect()
+    }
+}
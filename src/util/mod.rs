@@ -1,7 +1,21 @@
 pub mod auth;
+pub mod hostkey;
+pub mod interactive;
+pub mod pattern;
+pub mod secret;
 pub mod shell;
+pub mod ssh_config;
+pub mod timeout;
+pub mod tunnel;
 pub mod validator;
 
 pub use auth::*;
+pub use hostkey::*;
+pub use interactive::*;
+pub use pattern::*;
+pub use secret::*;
 pub use shell::*;
+pub use ssh_config::*;
+pub use timeout::*;
+pub use tunnel::*;
 pub use validator::*;
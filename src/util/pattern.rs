@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::{AppError, FileFilter, PatternSyntax, Source};
+
+/// 転送対象ファイルを選別するためのマッチャー
+/// Matcher used to select the files to transfer
+///
+/// パターンはファイル名に対して評価する。`/` を含むglobパターンのみ、
+/// ルートからの相対パスに対して評価する。
+/// Patterns are evaluated against the file name. Only glob patterns containing `/`
+/// are evaluated against the path relative to the root.
+#[derive(Debug, Clone)]
+pub struct FileMatcher {
+    path_glob: Option<CompiledPattern>,
+    include: Vec<CompiledPattern>,
+    exclude: Vec<CompiledPattern>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledPattern {
+    regex: Regex,
+    match_full_path: bool,
+}
+
+impl CompiledPattern {
+    fn new(pattern: &str, syntax: &PatternSyntax) -> Result<Self, AppError> {
+        let (expr, match_full_path) = match syntax {
+            PatternSyntax::Glob => (glob_to_regex(pattern), pattern.contains('/')),
+            PatternSyntax::Regex => (pattern.to_string(), false),
+        };
+        let regex = Regex::new(&expr).map_err(|e| {
+            AppError::Validation(format!("Invalid file pattern '{}': {}", pattern, e))
+        })?;
+        Ok(CompiledPattern {
+            regex,
+            match_full_path,
+        })
+    }
+
+    fn is_match(&self, relative: &Path) -> bool {
+        if self.match_full_path {
+            let path = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            self.regex.is_match(&path)
+        } else {
+            relative
+                .file_name()
+                .map(|name| self.regex.is_match(&name.to_string_lossy()))
+                .unwrap_or(false)
+        }
+    }
+}
+
+impl FileMatcher {
+    pub fn new(include: &[String], exclude: &[String], syntax: &PatternSyntax) -> Result<Self, AppError> {
+        Ok(FileMatcher {
+            path_glob: None,
+            include: include
+                .iter()
+                .map(|p| CompiledPattern::new(p, syntax))
+                .collect::<Result<_, _>>()?,
+            exclude: exclude
+                .iter()
+                .map(|p| CompiledPattern::new(p, syntax))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn from_filter(filter: &FileFilter) -> Result<Self, AppError> {
+        let syntax = filter.syntax.clone().unwrap_or_default();
+        FileMatcher::new(
+            filter.include.as_deref().unwrap_or_default(),
+            filter.exclude.as_deref().unwrap_or_default(),
+            &syntax,
+        )
+    }
+
+    /// includeが空の場合はすべてのファイルを対象とし、excludeに一致したものを除外する
+    /// An empty include list selects every file; anything matching exclude is dropped
+    pub fn is_match(&self, relative: &Path) -> bool {
+        let path_matched = self.path_glob.as_ref().is_none_or(|p| p.is_match(relative));
+        let included = self.include.is_empty() || self.include.iter().any(|p| p.is_match(relative));
+        path_matched && included && !self.exclude.iter().any(|p| p.is_match(relative))
+    }
+}
+
+/// globパターンを正規表現に変換する（`*`, `**`, `?`, `[...]` をサポート）
+/// Convert a glob pattern to an anchored regular expression (supports `*`, `**`, `?`, `[...]`)
+pub fn glob_to_regex(glob: &str) -> String {
+    let mut expr = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    expr.push_str(".*");
+                } else {
+                    expr.push_str("[^/]*");
+                }
+            }
+            '?' => expr.push_str("[^/]"),
+            '[' => {
+                expr.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    expr.push('^');
+                }
+                for class_char in chars.by_ref() {
+                    if class_char == ']' {
+                        break;
+                    }
+                    if class_char == '\\' {
+                        expr.push('\\');
+                    }
+                    expr.push(class_char);
+                }
+                expr.push(']');
+            }
+            other => expr.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    expr.push('$');
+    expr
+}
+
+/// パスにglobのワイルドカードが含まれるかどうか
+/// Whether the path contains glob wildcards
+pub fn has_glob_chars(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// 転送元のディレクトリと選別条件の組
+/// A source directory together with the matcher selecting files inside it
+#[derive(Debug, Clone)]
+pub struct FileSelection {
+    pub root: PathBuf,
+    pub matcher: FileMatcher,
+}
+
+impl FileSelection {
+    /// `source.path` の末尾のglob（例: `/out/*.csv`）と `source.filter` から選別条件を組み立てる。
+    /// どちらも指定されていなければ `None` を返す。
+    /// Build the selection from a trailing glob in `source.path` (e.g. `/out/*.csv`) and `source.filter`.
+    /// Returns `None` when neither is set.
+    pub fn from_source(source: &Source) -> Result<Option<Self>, AppError> {
+        let path = Path::new(&source.path);
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let (root, path_glob) = if has_glob_chars(&file_name) {
+            let parent = path.parent().unwrap_or_else(|| Path::new("."));
+            if has_glob_chars(&parent.to_string_lossy()) {
+                return Err(AppError::Validation(format!(
+                    "Wildcards are only allowed in the last component of source path '{}'",
+                    source.path
+                )));
+            }
+            (parent.to_path_buf(), Some(file_name))
+        } else {
+            (path.to_path_buf(), None)
+        };
+
+        if path_glob.is_none() && source.filter.is_none() {
+            return Ok(None);
+        }
+
+        let mut matcher = match &source.filter {
+            Some(filter) => FileMatcher::from_filter(filter)?,
+            None => FileMatcher::new(&[], &[], &PatternSyntax::Glob)?,
+        };
+        if let Some(glob) = path_glob {
+            matcher.path_glob = Some(CompiledPattern::new(&glob, &PatternSyntax::Glob)?);
+        }
+
+        Ok(Some(FileSelection { root, matcher }))
+    }
+}
@@ -0,0 +1,18 @@
+use crate::AppError;
+use std::fs;
+
+/// 秘密情報の参照先から値を取得する。
+/// `file:<パス>` はファイルの内容（末尾の改行を除く）、`env:<名前>` または名前のみは環境変数の値を返す。
+/// Resolve a secret reference.
+/// `file:<path>` reads the file (without its trailing newline); `env:<name>` or a bare name reads an env var.
+pub fn resolve_secret_ref(reference: &str) -> Result<String, AppError> {
+    if let Some(path) = reference.strip_prefix("file:") {
+        let path = shellexpand::tilde(path.trim());
+        let content = fs::read_to_string(path.as_ref()).map_err(|e| {
+            AppError::Validation(format!("Failed to read secret file '{}': {}", path, e))
+        })?;
+        return Ok(content.trim_end_matches(['\r', '\n']).to_string());
+    }
+    let name = reference.strip_prefix("env:").unwrap_or(reference);
+    std::env::var(name).map_err(|_| AppError::EnvVarNotFound(name.to_string()))
+}
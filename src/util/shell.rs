@@ -1,3 +1,4 @@
+use crate::AppError;
 use anyhow::{Context, Result};
 use log::{debug, error, info, warn};
 use tokio::process::Command;
@@ -16,7 +17,15 @@ fn get_os_shell_command() -> (&'static str, &'static str) {
     ("sh", "-c") // Unix系OSでは'sh -c'が一般的
 }
 
-pub async fn execute_command(command_str: &str, profile_id: &str, job_type: &str) -> Result<()> {
+/// コマンドをシェルで実行し、終了コードを返す（シグナルで終了した場合は `None`）。
+/// 0以外で終了した場合は `AppError::CommandFailed` を返す。
+/// Run a command through the shell and return its exit code (`None` when killed by a signal).
+/// A non-zero exit is reported as `AppError::CommandFailed`.
+pub async fn execute_command(
+    command_str: &str,
+    profile_id: &str,
+    job_type: &str,
+) -> Result<Option<i32>> {
     info!(
         "Executing {} command for profile '{}': {}",
         job_type, profile_id, command_str
@@ -72,16 +81,17 @@ pub async fn execute_command(command_str: &str, profile_id: &str, job_type: &str
             profile_id,
             output.status.code()
         );
-        return Err(anyhow::anyhow!(
-            "{} command failed for profile '{}'",
-            job_type,
-            profile_id
-        ));
+        return Err(AppError::CommandFailed {
+            job_type: job_type.to_string(),
+            profile_id: profile_id.to_string(),
+            code: output.status.code(),
+        }
+        .into());
     } else {
         info!(
             "{} command completed successfully for profile '{}'.",
             job_type, profile_id
         );
     }
-    Ok(())
+    Ok(output.status.code())
 }
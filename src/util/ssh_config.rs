@@ -0,0 +1,292 @@
+use crate::{HostKeyPolicy, HostKeySettings, TimeoutSettings};
+use anyhow::{Context, Result};
+use dirs::home_dir;
+use log::{debug, warn};
+use ssh2::{MethodType, Session};
+use ssh2_config::{Algorithms, HostParams, ParseRule, SshConfig};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// IdentityFileが指定されていない場合にOpenSSHが試す鍵（この順に試す）
+// Keys OpenSSH tries when no IdentityFile is given (tried in this order)
+const DEFAULT_IDENTITY_FILES: [&str; 4] = ["id_rsa", "id_ecdsa", "id_ed25519", "id_dsa"];
+
+/// ssh_configから解決した接続設定
+/// Connection settings resolved from ssh_config
+#[derive(Debug, Clone, Default)]
+pub struct SshConfigSettings {
+    pub host_name: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    // 試行する秘密鍵（IdentityFileの記述順）
+    // Private keys to try, in IdentityFile order
+    pub identity_files: Vec<PathBuf>,
+    // `IdentitiesOnly yes` の場合はssh-agentの鍵を使わない
+    // With `IdentitiesOnly yes` keys held by ssh-agent are not used
+    pub identities_only: bool,
+    // ConnectTimeout / ServerAliveInterval
+    pub timeouts: TimeoutSettings,
+    // StrictHostKeyChecking / UserKnownHostsFile
+    pub host_keys: HostKeySettings,
+    pub ciphers: Option<String>,
+    pub kex_algorithms: Option<String>,
+    pub host_key_algorithms: Option<String>,
+    pub macs: Option<String>,
+    pub proxy_jump: Option<String>,
+}
+
+// 同じブロック内で繰り返されたIdentityFileをssh2-configは最後の1件で上書きするため、
+// 読み込み前にブロックごとのIdentityFileを取り出し、この接頭辞を付けた目印に置き換える
+// ssh2-config keeps only the last of repeated IdentityFile lines in a block, so each block's
+// IdentityFiles are collected before parsing and replaced by a marker with this prefix
+const IDENTITY_MARKER_PREFIX: &str = "vento-identity-files:";
+
+/// 読み込み済みのssh_config
+/// A parsed ssh_config
+#[derive(Debug, Clone, Default)]
+pub struct ParsedSshConfig {
+    config: SshConfig,
+    // 目印の番号ごとのIdentityFile（記述順）
+    // IdentityFiles per marker number, in the order they were written
+    identity_blocks: Vec<Vec<PathBuf>>,
+}
+
+/// ssh_configを読み込む。`path` が未指定の場合は ~/.ssh/config を使い、存在しなければ空の設定とする。
+/// Load an ssh_config file. `~/.ssh/config` is used when `path` is omitted, and an empty
+/// configuration when that file does not exist.
+pub fn load_ssh_config(path: Option<&str>) -> Result<ParsedSshConfig> {
+    let path = match path {
+        Some(path) => PathBuf::from(shellexpand::tilde(path).as_ref()),
+        None => {
+            let path = home_dir()
+                .context("No home dir found for resolving ~/.ssh/config")?
+                .join(".ssh")
+                .join("config");
+            if !path.exists() {
+                debug!("SSH config '{}' does not exist.", path.display());
+                return Ok(ParsedSshConfig::default());
+            }
+            path
+        }
+    };
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read SSH config: '{}'", path.display()))?;
+    parse_ssh_config(&content)
+        .with_context(|| format!("Failed to parse SSH config: '{}'", path.display()))
+}
+
+/// ssh_configの内容を解析する
+/// Parse the contents of an ssh_config file
+pub fn parse_ssh_config(content: &str) -> Result<ParsedSshConfig> {
+    let mut identity_blocks: Vec<Vec<PathBuf>> = Vec::new();
+    let mut current_block: Option<usize> = None;
+    let mut lines = Vec::new();
+
+    for line in content.lines() {
+        let Some((keyword, args)) = split_keyword(line) else {
+            lines.push(line.to_string());
+            continue;
+        };
+        if keyword.eq_ignore_ascii_case("host") {
+            current_block = None;
+        } else if keyword.eq_ignore_ascii_case("identityfile") {
+            let index = match current_block {
+                Some(index) => index,
+                None => {
+                    identity_blocks.push(Vec::new());
+                    let index = identity_blocks.len() - 1;
+                    lines.push(format!("IdentityFile {}{}", IDENTITY_MARKER_PREFIX, index));
+                    current_block = Some(index);
+                    index
+                }
+            };
+            identity_blocks[index].push(expand_identity_path(args));
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+
+    let config = SshConfig::default().parse(
+        &mut lines.join("\n").as_bytes(),
+        ParseRule::ALLOW_UNKNOWN_FIELDS | ParseRule::ALLOW_UNSUPPORTED_FIELDS,
+    )?;
+    Ok(ParsedSshConfig {
+        config,
+        identity_blocks,
+    })
+}
+
+impl ParsedSshConfig {
+    // 一致するすべてのHostブロックのIdentityFileを記述順に集める（OpenSSHと同じく重複は除く）
+    // Collect the IdentityFiles of every matching Host block in order (without duplicates, like OpenSSH)
+    fn identity_files(&self, alias: &str) -> Option<Vec<PathBuf>> {
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut found = false;
+        for host in self.config.intersecting_hosts(alias) {
+            let Some(entries) = &host.params.identity_file else {
+                continue;
+            };
+            found = true;
+            for entry in entries {
+                // Includeされたファイルの値は目印に置き換えられていない
+                // Values from included files are not replaced by markers
+                let block = entry
+                    .to_str()
+                    .and_then(|entry| entry.strip_prefix(IDENTITY_MARKER_PREFIX))
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .and_then(|index| self.identity_blocks.get(index));
+                let resolved = match block {
+                    Some(block) => block.clone(),
+                    None => vec![expand_identity_path(&entry.to_string_lossy())],
+                };
+                for file in resolved {
+                    if !files.contains(&file) {
+                        files.push(file);
+                    }
+                }
+            }
+        }
+        found.then_some(files)
+    }
+}
+
+// `Keyword value` / `Keyword=value` をキーワードと値に分ける（空行とコメントは除く）
+// Split `Keyword value` / `Keyword=value` into the keyword and value (skipping blanks and comments)
+fn split_keyword(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let end = line
+        .find(|c: char| c.is_whitespace() || c == '=')
+        .unwrap_or(line.len());
+    let (keyword, rest) = line.split_at(end);
+    Some((keyword, rest.trim_start().trim_start_matches('=').trim()))
+}
+
+fn expand_identity_path(value: &str) -> PathBuf {
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    resolve_home_relative(Path::new(shellexpand::tilde(value).as_ref()))
+}
+
+impl SshConfigSettings {
+    /// 接続先のポート。プロファイルやProxyJumpの `host:port` で明示されたポートを優先し、
+    /// なければssh_configの `Port`、それもなければ22を使う
+    /// Port to connect to. A port given explicitly in the profile or a ProxyJump `host:port` wins,
+    /// then ssh_config's `Port`, then 22
+    pub fn port_or(&self, explicit: Option<u16>) -> u16 {
+        explicit.or(self.port).unwrap_or(22)
+    }
+
+    /// エイリアスに一致するHostブロックの設定を解決する
+    /// Resolve the settings of the Host blocks matching the alias
+    pub fn resolve(config: &ParsedSshConfig, alias: &str) -> Self {
+        let params = config.config.query(alias);
+        let identity_files = config
+            .identity_files(alias)
+            .unwrap_or_else(default_identity_files);
+
+        let policy = first_arg(&params, "stricthostkeychecking").and_then(|value| {
+            match value.to_ascii_lowercase().as_str() {
+                "yes" | "ask" => Some(HostKeyPolicy::Strict),
+                "accept-new" => Some(HostKeyPolicy::AcceptNew),
+                "no" | "off" => Some(HostKeyPolicy::Off),
+                other => {
+                    warn!("Ignoring unknown StrictHostKeyChecking value '{}'", other);
+                    None
+                }
+            }
+        });
+        let known_hosts_file = first_arg(&params, "userknownhostsfile")
+            .filter(|file| !file.eq_ignore_ascii_case("none"))
+            .map(str::to_string);
+
+        Self {
+            host_name: params.host_name.clone(),
+            port: params.port,
+            user: params.user.clone(),
+            identity_files,
+            identities_only: first_arg(&params, "identitiesonly")
+                .is_some_and(|value| value.eq_ignore_ascii_case("yes")),
+            timeouts: TimeoutSettings {
+                connect_secs: params.connect_timeout.map(|timeout| timeout.as_secs()),
+                keepalive_secs: params
+                    .server_alive_interval
+                    .map(|interval| interval.as_secs().min(u32::MAX as u64) as u32),
+                ..Default::default()
+            },
+            host_keys: HostKeySettings {
+                policy,
+                known_hosts_file,
+            },
+            ciphers: algorithm_list(&params.ciphers),
+            kex_algorithms: algorithm_list(&params.kex_algorithms),
+            host_key_algorithms: algorithm_list(&params.host_key_algorithms),
+            macs: algorithm_list(&params.mac),
+            proxy_jump: params
+                .unsupported_fields
+                .get("proxyjump")
+                .map(|args| args.join(",")),
+        }
+    }
+
+    /// 暗号・鍵交換・ホスト鍵・MACのアルゴリズムをハンドシェイク前のセッションに設定する
+    /// Apply the cipher, key exchange, host key and MAC algorithms to a session before the handshake
+    pub fn apply_algorithms(&self, sess: &Session) -> Result<()> {
+        let prefs = [
+            (&self.kex_algorithms, MethodType::Kex, "KexAlgorithms"),
+            (&self.host_key_algorithms, MethodType::HostKey, "HostKeyAlgorithms"),
+            (&self.ciphers, MethodType::CryptCs, "Ciphers"),
+            (&self.ciphers, MethodType::CryptSc, "Ciphers"),
+            (&self.macs, MethodType::MacCs, "MACs"),
+            (&self.macs, MethodType::MacSc, "MACs"),
+        ];
+        for (value, method_type, name) in prefs {
+            if let Some(value) = value {
+                debug!("Using {} from SSH config: {}", name, value);
+                sess.method_pref(method_type, value).with_context(|| {
+                    format!("None of the {} from SSH config are supported: {}", name, value)
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn first_arg<'a>(params: &'a HostParams, field: &str) -> Option<&'a str> {
+    params
+        .unsupported_fields
+        .get(field)
+        .and_then(|args| args.first())
+        .map(String::as_str)
+}
+
+// 既定値から変更されたアルゴリズムだけをlibssh2の形式（カンマ区切り）で返す
+// Only algorithms changed from the defaults, in libssh2's comma separated form
+fn algorithm_list(algorithms: &Algorithms) -> Option<String> {
+    (!algorithms.is_default()).then(|| algorithms.algorithms().join(","))
+}
+
+fn resolve_home_relative(path: &Path) -> PathBuf {
+    match home_dir() {
+        Some(home) if path.is_relative() => home.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn default_identity_files() -> Vec<PathBuf> {
+    let Some(ssh_dir) = home_dir().map(|home| home.join(".ssh")) else {
+        return Vec::new();
+    };
+    DEFAULT_IDENTITY_FILES
+        .iter()
+        .map(|name| ssh_dir.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
@@ -0,0 +1,134 @@
+use crate::{AppError, TimeoutSettings};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+use ssh2::ErrorCode;
+use std::{
+    io,
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// 既定のTCP接続タイムアウト（秒）
+/// Default TCP connect timeout (seconds)
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 30;
+/// 既定のSSHハンドシェイク・認証タイムアウト（秒）
+/// Default SSH handshake and authentication timeout (seconds)
+pub const DEFAULT_HANDSHAKE_TIMEOUT_SECS: u64 = 30;
+/// 既定の操作タイムアウト（秒）
+/// Default timeout of each blocking operation (seconds)
+pub const DEFAULT_OPERATION_TIMEOUT_SECS: u64 = 300;
+/// 既定のキープアライブ間隔（秒）
+/// Default keepalive interval (seconds)
+pub const DEFAULT_KEEPALIVE_SECS: u32 = 60;
+
+// libssh2のタイムアウトを示すエラーコード（LIBSSH2_ERROR_TIMEOUT / LIBSSH2_ERROR_SOCKET_TIMEOUT）
+// libssh2 error codes that indicate a timeout (LIBSSH2_ERROR_TIMEOUT / LIBSSH2_ERROR_SOCKET_TIMEOUT)
+const LIBSSH2_TIMEOUT_ERRORS: [i32; 2] = [-9, -30];
+
+fn secs_to_duration(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+impl TimeoutSettings {
+    pub fn connect_secs(&self) -> u64 {
+        self.connect_secs.unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS)
+    }
+
+    pub fn handshake_secs(&self) -> u64 {
+        self.handshake_secs.unwrap_or(DEFAULT_HANDSHAKE_TIMEOUT_SECS)
+    }
+
+    pub fn operation_secs(&self) -> u64 {
+        self.operation_secs.unwrap_or(DEFAULT_OPERATION_TIMEOUT_SECS)
+    }
+
+    pub fn keepalive_secs(&self) -> u32 {
+        self.keepalive_secs.unwrap_or(DEFAULT_KEEPALIVE_SECS)
+    }
+
+    pub fn connect(&self) -> Option<Duration> {
+        secs_to_duration(self.connect_secs())
+    }
+
+    /// libssh2の `set_timeout` に渡すミリ秒（0はタイムアウトなし）
+    /// Milliseconds for libssh2's `set_timeout` (0 means no timeout)
+    pub fn handshake_ms(&self) -> u32 {
+        secs_to_ms(self.handshake_secs())
+    }
+
+    pub fn operation_ms(&self) -> u32 {
+        secs_to_ms(self.operation_secs())
+    }
+}
+
+fn secs_to_ms(secs: u64) -> u32 {
+    secs.saturating_mul(1000).min(u32::MAX as u64) as u32
+}
+
+/// タイムアウト付きでTCP接続する。名前解決で得たアドレスを順に試す。
+/// Open a TCP connection with a timeout, trying each resolved address in turn.
+pub fn connect_tcp(host: &str, port: u16, timeouts: &TimeoutSettings) -> Result<TcpStream> {
+    let addrs: Vec<_> = (host, port)
+        .to_socket_addrs()
+        .with_context(|| format!("Failed to resolve {}:{}", host, port))?
+        .collect();
+
+    let mut last_error = None;
+    for addr in &addrs {
+        let result = match timeouts.connect() {
+            Some(timeout) => TcpStream::connect_timeout(addr, timeout),
+            None => TcpStream::connect(addr),
+        };
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(e) => {
+                debug!("Connection to {} failed: {}", addr, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    match last_error {
+        Some(e) if e.kind() == io::ErrorKind::TimedOut => Err(AppError::Timeout {
+            operation: format!("connecting to {}:{}", host, port),
+            secs: timeouts.connect_secs(),
+        }
+        .into()),
+        Some(e) => Err(anyhow::Error::new(e).context(format!("Failed to connect to {}:{}", host, port))),
+        None => Err(anyhow!("No addresses found for {}:{}", host, port)),
+    }
+}
+
+/// エラーの原因にタイムアウトが含まれているかを判定する
+/// Whether any cause of the error is a timeout
+pub fn is_timeout(err: &anyhow::Error) -> bool {
+    if let Some(AppError::Timeout { .. }) = err.downcast_ref::<AppError>() {
+        return true;
+    }
+    err.chain().any(|cause| {
+        if let Some(AppError::Timeout { .. }) = cause.downcast_ref::<AppError>() {
+            return true;
+        }
+        if let Some(ssh_error) = cause.downcast_ref::<ssh2::Error>() {
+            return matches!(ssh_error.code(), ErrorCode::Session(code) if LIBSSH2_TIMEOUT_ERRORS.contains(&code));
+        }
+        if let Some(io_error) = cause.downcast_ref::<io::Error>() {
+            return io_error.kind() == io::ErrorKind::TimedOut;
+        }
+        false
+    })
+}
+
+/// libssh2やI/Oのタイムアウトを `AppError::Timeout` として表面化させる
+/// Surface a libssh2 or I/O timeout as `AppError::Timeout`
+pub fn surface_timeout(err: anyhow::Error, operation: &str, secs: u64) -> anyhow::Error {
+    if !is_timeout(&err) || matches!(err.downcast_ref::<AppError>(), Some(AppError::Timeout { .. })) {
+        return err;
+    }
+    warn!("Timed out while {}: {:#}", operation, err);
+    AppError::Timeout {
+        operation: operation.to_string(),
+        secs,
+    }
+    .into()
+}
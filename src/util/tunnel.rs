@@ -0,0 +1,209 @@
+use crate::{Authentication, AuthenticationMethod, JumpHost};
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use ssh2::{Channel, Session};
+use std::{
+    io::{self, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+// 中継データのバッファサイズ
+// Buffer size used when relaying data
+const RELAY_BUFFER_SIZE: usize = 32 * 1024;
+// どちら向きにもデータがない場合の待ち時間
+// Pause when neither direction has data
+const RELAY_IDLE_SLEEP: Duration = Duration::from_millis(1);
+
+impl JumpHost {
+    /// 踏み台ホストへの接続に使う認証情報。
+    /// 未指定の場合は接続先の認証情報を引き継ぐ（sshConfigの場合はエイリアスを踏み台ホスト名にする）。
+    /// Authentication used for the jump host.
+    /// Falls back to the target's authentication (with the jump host as alias for sshConfig).
+    pub fn effective_authentication(&self, target: &Authentication) -> Authentication {
+        match &self.authentication {
+            Some(auth) => auth.clone(),
+            None => {
+                let mut auth = target.clone();
+                auth.jump_hosts = None;
+                auth.host_key_fingerprint = None;
+                if auth.method == AuthenticationMethod::SshConfig {
+                    auth.ssh_config_alias = Some(self.host.clone());
+                }
+                auth
+            }
+        }
+    }
+}
+
+/// ssh_configの `ProxyJump` の値（`[user@]host[:port]` のカンマ区切り）を踏み台ホストの一覧に変換する
+/// Convert an ssh_config `ProxyJump` value (comma separated `[user@]host[:port]`) into jump hosts
+pub fn parse_proxy_jump(value: &str, target: &Authentication) -> Vec<JumpHost> {
+    if value.trim().eq_ignore_ascii_case("none") {
+        return Vec::new();
+    }
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let entry = entry.strip_prefix("ssh://").unwrap_or(entry);
+            let (user, host_port) = match entry.rsplit_once('@') {
+                Some((user, rest)) => (Some(user), rest),
+                None => (None, entry),
+            };
+            let (host, port) = split_host_port(host_port);
+            let authentication = Authentication {
+                method: AuthenticationMethod::SshConfig,
+                username: user.map_or_else(|| target.username.clone(), str::to_string),
+                password_ref: None,
+                private_key_ref: None,
+                passphrase_ref: target.passphrase_ref.clone(),
+                ssh_config_alias: Some(host.clone()),
+                ssh_config_file: target.ssh_config_file.clone(),
+                prompts: None,
+                host_key_fingerprint: None,
+                jump_hosts: None,
+            };
+            JumpHost {
+                host,
+                port,
+                authentication: Some(authentication),
+            }
+        })
+        .collect()
+}
+
+// `host:port` / `[v6addr]:port` / `host` を分割する
+// Split `host:port` / `[v6addr]:port` / `host`
+fn split_host_port(value: &str) -> (String, Option<u16>) {
+    if let Some(rest) = value.strip_prefix('[')
+        && let Some((host, port)) = rest.split_once(']')
+    {
+        let port = port.strip_prefix(':').and_then(|p| p.parse().ok());
+        return (host.to_string(), port);
+    }
+    match value.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => match port.parse() {
+            Ok(port) => (host.to_string(), Some(port)),
+            Err(_) => (value.to_string(), None),
+        },
+        _ => (value.to_string(), None),
+    }
+}
+
+/// 踏み台ホストのセッション上で `channel_direct_tcpip` により接続先へのトンネルを開き、
+/// ローカルのループバック接続として返す。中継は専用のスレッドで行う。
+/// Open a tunnel to the target with `channel_direct_tcpip` on the jump host session and
+/// expose it as a local loopback connection. Data is relayed on a dedicated thread.
+pub fn open_tunnel(bastion: Session, host: &str, port: u16) -> Result<TcpStream> {
+    let channel = bastion
+        .channel_direct_tcpip(host, port, None)
+        .with_context(|| format!("Failed to open a tunnel to {}:{} via the jump host", host, port))?;
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .context("Failed to bind a local port for the tunnel")?;
+    let local_addr = listener.local_addr()?;
+    let client = TcpStream::connect(local_addr)
+        .context("Failed to connect to the local tunnel endpoint")?;
+    let client_addr = client.local_addr()?;
+
+    // 他のプロセスからの接続を中継しないよう、自分の接続だけを受け入れる
+    // Accept only our own connection so that no other process can use the tunnel
+    let relay_socket = loop {
+        let (socket, peer) = listener.accept()?;
+        if peer == client_addr {
+            break socket;
+        }
+        warn!("Rejected unexpected connection to the tunnel from {}", peer);
+    };
+    drop(listener);
+
+    let target = format!("{}:{}", host, port);
+    thread::Builder::new()
+        .name(format!("vento-tunnel-{}", target))
+        .spawn(move || {
+            if let Err(e) = relay(&bastion, channel, relay_socket) {
+                debug!("Tunnel to {} closed with error: {}", target, e);
+            } else {
+                debug!("Tunnel to {} closed.", target);
+            }
+        })
+        .context("Failed to start the tunnel thread")?;
+
+    info!("Tunnel established to {}:{}", host, port);
+    Ok(client)
+}
+
+// ローカルソケットとSSHチャネルの間でデータを中継する
+// Relay data between the local socket and the SSH channel
+fn relay(session: &Session, mut channel: Channel, mut socket: TcpStream) -> io::Result<()> {
+    session.set_blocking(false);
+    socket.set_nonblocking(true)?;
+
+    let mut buf = vec![0u8; RELAY_BUFFER_SIZE];
+    let mut socket_open = true;
+    loop {
+        let mut progressed = false;
+
+        if socket_open {
+            match socket.read(&mut buf) {
+                Ok(0) => {
+                    socket_open = false;
+                    retry_would_block(|| channel.send_eof().map_err(io::Error::from))?;
+                }
+                Ok(n) => {
+                    write_all_retrying(&mut channel, &buf[..n])?;
+                    progressed = true;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        match channel.read(&mut buf) {
+            Ok(0) if channel.eof() => break,
+            Ok(0) => {}
+            Ok(n) => {
+                write_all_retrying(&mut socket, &buf[..n])?;
+                progressed = true;
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+
+        if !progressed {
+            let _ = session.keepalive_send();
+            thread::sleep(RELAY_IDLE_SLEEP);
+        }
+    }
+
+    let _ = retry_would_block(|| channel.close().map_err(io::Error::from));
+    Ok(())
+}
+
+// ノンブロッキングの書き込み先にすべて書き込む
+// Write everything to a non-blocking writer
+fn write_all_retrying<W: Write>(writer: &mut W, mut data: &[u8]) -> io::Result<()> {
+    // `Channel::flush` は受信バッファを破棄するため呼び出さない
+    // `Channel::flush` discards the read buffer, so it is deliberately not called
+    while !data.is_empty() {
+        match writer.write(data) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => data = &data[n..],
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(RELAY_IDLE_SLEEP),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn retry_would_block<F: FnMut() -> io::Result<()>>(mut op: F) -> io::Result<()> {
+    loop {
+        match op() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(RELAY_IDLE_SLEEP),
+            other => return other,
+        }
+    }
+}
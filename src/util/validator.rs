@@ -25,3 +25,9 @@ pub fn validate_cross_platform_path(val: &str) -> Result<(), ValidationError> {
 
     Ok(())
 }
+
+// 転送元パスではglobのワイルドカード（`*`, `?`）を許可する
+// Source paths may contain glob wildcards (`*`, `?`)
+pub fn validate_source_path(val: &str) -> Result<(), ValidationError> {
+    validate_cross_platform_path(&val.replace(['*', '?'], "_"))
+}
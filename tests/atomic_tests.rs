@@ -0,0 +1,43 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+use vento::{commit_local_file, AtomicDelivery};
+
+#[test]
+fn test_temp_path_defaults_to_part_suffix() {
+    let atomic = AtomicDelivery::default();
+    assert_eq!(
+        atomic.temp_path(Path::new("/upload/report.csv")),
+        PathBuf::from("/upload/report.csv.part")
+    );
+}
+
+#[test]
+fn test_temp_path_with_custom_prefix() {
+    let atomic = AtomicDelivery {
+        temp_prefix: Some(".".into()),
+        temp_suffix: None,
+    };
+    assert_eq!(
+        atomic.temp_path(Path::new("/upload/report.csv")),
+        PathBuf::from("/upload/.report.csv")
+    );
+}
+
+#[test]
+fn test_local_temp_file_is_persisted_to_destination() {
+    let dir = tempdir().unwrap();
+    let dst = dir.path().join("report.csv");
+    let atomic = AtomicDelivery::default();
+
+    let mut temp_file = atomic.local_temp_file(&dst).unwrap();
+    assert_eq!(temp_file.path().parent(), Some(dir.path()));
+    write!(temp_file, "payload").unwrap();
+    assert!(!dst.exists());
+
+    commit_local_file(temp_file, &dst).unwrap();
+    assert_eq!(fs::read_to_string(&dst).unwrap(), "payload");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
@@ -0,0 +1,128 @@
+use std::io::{self, Cursor};
+use std::time::{Duration, Instant};
+
+use chrono::NaiveTime;
+use vento::*;
+
+fn time(value: &str) -> NaiveTime {
+    value.parse().unwrap()
+}
+
+#[test]
+fn test_parse_bandwidth_units() {
+    assert_eq!("10MiB/s".parse(), Ok(Bandwidth::BytesPerSec(10 * 1024 * 1024)));
+    assert_eq!("500KB/s".parse(), Ok(Bandwidth::BytesPerSec(500_000)));
+    assert_eq!("1.5 MiB".parse(), Ok(Bandwidth::BytesPerSec(1_572_864)));
+    assert_eq!("2048".parse(), Ok(Bandwidth::BytesPerSec(2048)));
+    assert_eq!("Unlimited".parse(), Ok(Bandwidth::Unlimited));
+    assert!("10 furlongs/s".parse::<Bandwidth>().is_err());
+    assert!("0MiB/s".parse::<Bandwidth>().is_err());
+    assert_eq!(Bandwidth::BytesPerSec(5 * 1024 * 1024).to_string(), "5MiB/s");
+    assert_eq!(Bandwidth::BytesPerSec(1500).to_string(), "1500B/s");
+}
+
+#[test]
+fn test_deserialize_fixed_and_scheduled_limits() {
+    let fixed: BandwidthLimit = serde_yaml::from_str("10MiB/s").unwrap();
+    assert_eq!(fixed, BandwidthLimit::Fixed(Bandwidth::BytesPerSec(10 * 1024 * 1024)));
+
+    let scheduled: BandwidthLimit = serde_yaml::from_str(
+        r#"
+windows:
+  - from: "09:00"
+    to: "18:00"
+    limit: 5MiB/s
+"#,
+    )
+    .unwrap();
+    assert_eq!(scheduled.bytes_per_sec_at(time("12:30")), Some(5 * 1024 * 1024));
+    assert_eq!(scheduled.bytes_per_sec_at(time("18:00")), None);
+    assert_eq!(scheduled.bytes_per_sec_at(time("02:00")), None);
+
+    assert!(serde_yaml::from_str::<BandwidthLimit>("fast").is_err());
+}
+
+#[test]
+fn test_schedule_windows_wrap_past_midnight() {
+    let limit = BandwidthLimit::Scheduled(BandwidthSchedule {
+        default: Some(Bandwidth::BytesPerSec(1000)),
+        windows: vec![BandwidthWindow {
+            from: time("22:00"),
+            to: time("06:00"),
+            limit: Bandwidth::Unlimited,
+        }],
+    });
+    assert_eq!(limit.bytes_per_sec_at(time("23:15")), None);
+    assert_eq!(limit.bytes_per_sec_at(time("05:59")), None);
+    assert_eq!(limit.bytes_per_sec_at(time("06:00")), Some(1000));
+}
+
+#[test]
+fn test_throttle_uses_the_lowest_limit() {
+    let mut throttle = Throttle::new(vec![
+        BandwidthLimit::Fixed(Bandwidth::BytesPerSec(4000)),
+        BandwidthLimit::Fixed(Bandwidth::Unlimited),
+        BandwidthLimit::Fixed(Bandwidth::BytesPerSec(1000)),
+    ]);
+    assert_eq!(throttle.rate(), Some(1000));
+    // 1回の読み込みはバケットの容量（250ms分）までに抑えられる
+    // A single read is capped at the bucket capacity (250ms worth)
+    assert_eq!(throttle.max_chunk(8192), 250);
+    assert_eq!(throttle.consume(250), Duration::ZERO);
+    assert!(throttle.consume(500) >= Duration::from_millis(400));
+
+    let mut unlimited = Throttle::new(Vec::new());
+    assert_eq!(unlimited.rate(), None);
+    assert_eq!(unlimited.max_chunk(8192), 8192);
+    assert_eq!(unlimited.consume(1 << 30), Duration::ZERO);
+}
+
+#[test]
+fn test_throttled_reader_limits_throughput() {
+    let data = vec![1u8; 192 * 1024];
+    let throttle = Throttle::new(vec![BandwidthLimit::Fixed(Bandwidth::BytesPerSec(256 * 1024))]);
+    let mut reader = ThrottledReader::new(Cursor::new(data), throttle);
+
+    let started = Instant::now();
+    let copied = io::copy(&mut reader, &mut io::sink()).unwrap();
+    assert_eq!(copied, 192 * 1024);
+    // 最初の64KiBはバケットから即座に読め、残り128KiBに約0.5秒かかる
+    // The first 64KiB come straight from the bucket; the remaining 128KiB take about 0.5s
+    assert!(started.elapsed() >= Duration::from_millis(400));
+}
+
+#[test]
+fn test_profile_and_app_config_accept_bandwidth_limit() {
+    let config: AppConfig = serde_yaml::from_str("bandwidthLimit: 20MiB/s\n").unwrap();
+    let context = TransferContext::default().with_bandwidth_cap(config.bandwidth_limit);
+    let profile = TransferProfile {
+        bandwidth_limit: Some(BandwidthLimit::Fixed(Bandwidth::BytesPerSec(5 * 1024 * 1024))),
+        ..Default::default()
+    };
+    assert_eq!(
+        Throttle::for_profile(&profile, &context).rate(),
+        Some(5 * 1024 * 1024)
+    );
+}
+
+#[test]
+fn test_global_cap_is_shared_by_concurrent_transfers() {
+    let context = TransferContext::default().with_bandwidth_cap(Some(BandwidthLimit::Fixed(
+        Bandwidth::BytesPerSec(256 * 1024),
+    )));
+    let profile = TransferProfile::default();
+
+    let started = Instant::now();
+    std::thread::scope(|scope| {
+        for _ in 0..2 {
+            let throttle = Throttle::for_profile(&profile, &context);
+            scope.spawn(move || {
+                let mut reader = ThrottledReader::new(Cursor::new(vec![1u8; 192 * 1024]), throttle);
+                io::copy(&mut reader, &mut io::sink()).unwrap();
+            });
+        }
+    });
+    // 2つの転送の合計384KiBが1つのバケットから引かれるため、約1.25秒かかる（別々なら約0.5秒）
+    // The combined 384KiB draw from one bucket and take about 1.25s (about 0.5s if separate)
+    assert!(started.elapsed() >= Duration::from_millis(1000));
+}
@@ -0,0 +1,59 @@
+// シグナルを受け取るため、他のテストとは別のテストバイナリにしている
+// Kept in its own test binary because it delivers signals to the process
+#![cfg(unix)]
+
+use std::process::Command;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+use vento::*;
+
+fn send_signal(name: &str) {
+    let status = Command::new("kill")
+        .arg(format!("-{}", name))
+        .arg(std::process::id().to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test]
+async fn test_daemon_reloads_on_sighup_and_stops_on_sigterm() {
+    let dir = tempfile::tempdir().unwrap();
+    let app_config: AppConfig = serde_yaml::from_str(&format!(
+        "scheduleStateFile: {}\n",
+        dir.path().join("schedule_state.json").display()
+    ))
+    .unwrap();
+    let profiles = Profile {
+        transfer_profiles: Vec::new(),
+    };
+    let reloads = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&reloads);
+    let reload_config = app_config.clone();
+
+    let daemon = tokio::spawn(run_daemon(app_config, profiles, move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Ok((
+            reload_config.clone(),
+            Profile {
+                transfer_profiles: Vec::new(),
+            },
+        ))
+    }));
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    send_signal("HUP");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(reloads.load(Ordering::SeqCst), 1);
+
+    send_signal("TERM");
+    let result = tokio::time::timeout(Duration::from_secs(5), daemon)
+        .await
+        .expect("daemon did not stop after SIGTERM")
+        .unwrap();
+    assert!(result.is_ok());
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+use vento::*;
+
+fn local_profile(on_success: SourceDisposition) -> TransferProfile {
+    let mut profile = TransferProfile::default();
+    profile.source.on_success = Some(on_success);
+    profile
+}
+
+fn transferred(source: PathBuf, relative: &str) -> TransferredFile {
+    TransferredFile {
+        source,
+        destination: PathBuf::from("/remote/dest"),
+        relative: PathBuf::from(relative),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_on_success_yaml_forms() {
+    let source: Source = serde_yaml::from_str(
+        r#"
+type: local
+path: /out
+trigger:
+  type: manual
+onSuccess:
+  moveTo: /out/archive
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        source.on_success,
+        Some(SourceDisposition::MoveTo("/out/archive".into()))
+    );
+
+    let source: Source = serde_yaml::from_str(
+        r#"
+type: local
+path: /out
+trigger:
+  type: manual
+onSuccess: delete
+"#,
+    )
+    .unwrap();
+    assert_eq!(source.on_success, Some(SourceDisposition::Delete));
+}
+
+#[test]
+fn test_dispose_local_delete() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("report.csv");
+    fs::write(&file, "data").unwrap();
+
+    let profile = local_profile(SourceDisposition::Delete);
+    dispose_sources(&profile, &[transferred(file.clone(), "report.csv")], None).unwrap();
+    assert!(!file.exists());
+}
+
+#[test]
+fn test_dispose_local_move_to_keeps_relative_path() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("daily")).unwrap();
+    let file = dir.path().join("daily/report.csv");
+    fs::write(&file, "data").unwrap();
+    let archive = dir.path().join("archive");
+
+    let profile = local_profile(SourceDisposition::MoveTo(archive.display().to_string()));
+    dispose_sources(&profile, &[transferred(file.clone(), "daily/report.csv")], None).unwrap();
+
+    assert!(!file.exists());
+    assert_eq!(
+        fs::read_to_string(archive.join("daily/report.csv")).unwrap(),
+        "data"
+    );
+}
+
+#[test]
+fn test_dispose_local_rename_suffix() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("report.csv");
+    fs::write(&file, "data").unwrap();
+
+    let profile = local_profile(SourceDisposition::RenameSuffix(".done".into()));
+    dispose_sources(&profile, &[transferred(file.clone(), "report.csv")], None).unwrap();
+
+    assert!(!file.exists());
+    assert!(dir.path().join("report.csv.done").exists());
+}
+
+#[test]
+fn test_dispose_remote_requires_transfer_session() {
+    let mut profile = local_profile(SourceDisposition::Delete);
+    profile.source.kind = SourceType::Sftp;
+
+    // 転送に使ったセッションがなければ、再接続せずに失敗する
+    // Without the session used for the transfer it fails instead of reconnecting
+    let err = dispose_sources(&profile, &[transferred(PathBuf::from("/outbox/report.csv"), "report.csv")], None)
+        .unwrap_err();
+    assert!(err.to_string().contains("session used for the transfer"));
+}
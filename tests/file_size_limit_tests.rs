@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use vento::*;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+#[test]
+fn test_profile_limit_overrides_context_limit() {
+    let context = TransferContext::default().with_max_file_size_mb(Some(500));
+    let profile = TransferProfile {
+        max_file_size_mb: Some(50 * 1024),
+        ..Default::default()
+    };
+    assert_eq!(context.max_file_size_mb(&profile), Some(50 * 1024));
+    assert!(context
+        .ensure_size_allowed(&profile, Path::new("/dumps/db.dump"), 40 * GIB)
+        .is_ok());
+
+    let err = context
+        .ensure_size_allowed(&TransferProfile::default(), Path::new("/dumps/db.dump"), 40 * GIB)
+        .unwrap_err();
+    assert!(matches!(err, AppError::FileTooLarge { limit_mb: 500, .. }));
+}
+
+#[test]
+fn test_zero_or_unset_limit_is_unlimited() {
+    let unlimited = TransferProfile {
+        max_file_size_mb: Some(0),
+        ..Default::default()
+    };
+    let context = TransferContext::default().with_max_file_size_mb(Some(500));
+    assert_eq!(context.max_file_size_mb(&unlimited), None);
+    assert!(context
+        .ensure_size_allowed(&unlimited, Path::new("huge.bin"), 50 * GIB)
+        .is_ok());
+
+    // ライブラリとして使う場合、上限を設定しなければ制限しない
+    // When used as a library, nothing is limited unless a limit is set
+    let context = TransferContext::default();
+    assert!(context
+        .ensure_size_allowed(&TransferProfile::default(), Path::new("huge.bin"), 50 * GIB)
+        .is_ok());
+}
+
+#[test]
+fn test_contexts_with_different_limits_coexist() {
+    let small = TransferContext::default().with_max_file_size_mb(Some(1));
+    let large = TransferContext::default().with_max_file_size_mb(Some(4096));
+    let profile = TransferProfile::default();
+    let size = 2 * 1024 * 1024;
+    assert!(small.ensure_size_allowed(&profile, Path::new("a"), size).is_err());
+    assert!(large.ensure_size_allowed(&profile, Path::new("a"), size).is_ok());
+}
+
+#[test]
+fn test_profile_deserializes_max_file_size_mb() {
+    let profile: TransferProfile = serde_yaml::from_str(
+        r#"
+profileId: big-dumps
+source:
+  type: local
+  path: /dumps/db.dump
+  trigger:
+    type: manual
+destination:
+  type: local
+  path: /backup
+transferProtocol:
+  protocol: SFTP
+maxFileSizeMb: 51200
+"#,
+    )
+    .unwrap();
+    assert_eq!(profile.max_file_size_mb, Some(51200));
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::PathBuf;
+use ssh2::{CheckResult, HostKeyType, Session};
+use tempfile::tempdir;
+
+use vento::*;
+
+const KEY: &[u8] = b"\x00\x00\x00\x0bssh-ed25519\x00\x00\x00\x20abcdefghijklmnopqrstuvwxyz012345";
+
+#[test]
+fn test_host_key_fingerprint_format() {
+    let fingerprint = host_key_fingerprint(KEY);
+    assert!(fingerprint.starts_with("SHA256:"));
+    // SHA256（32バイト）のパディングなしBase64は43文字
+    // Unpadded Base64 of a SHA256 digest (32 bytes) is 43 characters
+    assert_eq!(fingerprint.len(), "SHA256:".len() + 43);
+    assert!(!fingerprint.ends_with('='));
+}
+
+#[test]
+fn test_fingerprints_match_ignores_prefix_and_padding() {
+    let fingerprint = host_key_fingerprint(KEY);
+    let bare = fingerprint.trim_start_matches("SHA256:");
+    assert!(fingerprints_match(&fingerprint, &fingerprint));
+    assert!(fingerprints_match(bare, &fingerprint));
+    assert!(fingerprints_match(&format!("{}=", bare), &fingerprint));
+    assert!(!fingerprints_match("SHA256:AAAA", &fingerprint));
+}
+
+#[test]
+fn test_known_hosts_entry_name() {
+    assert_eq!(known_hosts_entry_name("example.com", 22), "example.com");
+    assert_eq!(known_hosts_entry_name("example.com", 2222), "[example.com]:2222");
+}
+
+#[test]
+fn test_host_key_policy_yaml() {
+    let settings: HostKeySettings = serde_yaml::from_str(
+        r#"
+policy: accept-new
+knownHostsFile: /etc/vento/known_hosts
+"#,
+    )
+    .unwrap();
+    assert_eq!(settings.policy(), HostKeyPolicy::AcceptNew);
+    assert_eq!(
+        settings.known_hosts_path().unwrap(),
+        PathBuf::from("/etc/vento/known_hosts")
+    );
+    assert_eq!(HostKeySettings::default().policy(), HostKeyPolicy::Strict);
+}
+
+#[test]
+fn test_profile_host_keys_fall_back_to_app_config() {
+    let app_config: AppConfig = serde_yaml::from_str(
+        r#"
+hostKeys:
+  policy: off
+  knownHostsFile: /etc/vento/known_hosts
+"#,
+    )
+    .unwrap();
+    let profile = TransferProfile {
+        host_keys: Some(HostKeySettings {
+            policy: Some(HostKeyPolicy::Strict),
+            known_hosts_file: None,
+        }),
+        ..Default::default()
+    };
+    let context = TransferContext::default().with_options(TransferOptions::from_app_config(&app_config));
+    let (_, host_keys) = context.connection_settings(&profile).resolve(None);
+    assert_eq!(host_keys.policy(), HostKeyPolicy::Strict);
+    assert_eq!(
+        host_keys.known_hosts_file.as_deref(),
+        Some("/etc/vento/known_hosts")
+    );
+}
+
+#[test]
+fn test_append_and_load_known_host() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("ssh/known_hosts");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    // 末尾に改行がなく、libssh2が解釈できない行を含む既存ファイル
+    // Existing file without a trailing newline and with a line libssh2 cannot parse
+    fs::write(&path, "@cert-authority *.example.com ssh-ed25519 AAAA").unwrap();
+
+    append_known_host(&path, "sftp.example.com", 2222, KEY, HostKeyType::Ed25519).unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("@cert-authority"));
+    assert!(content.contains("\n[sftp.example.com]:2222 ssh-ed25519 "));
+
+    let session = Session::new().unwrap();
+    let mut known_hosts = session.known_hosts().unwrap();
+    load_known_hosts(&mut known_hosts, &path).unwrap();
+    assert!(matches!(
+        known_hosts.check_port("sftp.example.com", 2222, KEY),
+        CheckResult::Match
+    ));
+    assert!(matches!(
+        known_hosts.check_port("sftp.example.com", 2222, b"other key"),
+        CheckResult::Mismatch
+    ));
+    assert!(matches!(
+        known_hosts.check_port("other.example.com", 22, KEY),
+        CheckResult::NotFound
+    ));
+}
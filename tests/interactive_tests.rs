@@ -0,0 +1,93 @@
+use std::borrow::Cow;
+use ssh2::{KeyboardInteractivePrompt, Prompt};
+
+use vento::*;
+
+fn interactive_auth(yaml: &str) -> Authentication {
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+#[test]
+fn test_keyboard_interactive_yaml_and_validation() {
+    let auth = interactive_auth(
+        r#"
+method: keyboard_interactive
+username: partner
+prompts:
+  - pattern: "(?i)verification code"
+    secretRef: PARTNER_OTP
+  - pattern: "(?i)password"
+    secretRef: file:/run/secrets/partner_password
+"#,
+    );
+    assert_eq!(auth.method, AuthenticationMethod::KeyboardInteractive);
+    assert_eq!(auth.prompts.as_ref().unwrap().len(), 2);
+    assert!(auth.validate().is_ok());
+}
+
+#[test]
+fn test_keyboard_interactive_requires_answers() {
+    let auth = interactive_auth(
+        r#"
+method: keyboard_interactive
+username: partner
+"#,
+    );
+    assert!(matches!(auth.validate(), Err(AppError::Validation(msg)) if msg.contains("prompts")));
+
+    let auth = interactive_auth(
+        r#"
+method: keyboard_interactive
+username: partner
+prompts:
+  - pattern: "(unclosed"
+    secretRef: PARTNER_OTP
+"#,
+    );
+    assert!(matches!(auth.validate(), Err(AppError::Validation(msg)) if msg.contains("(unclosed")));
+}
+
+#[test]
+fn test_prompt_responder_answers_matching_prompts() {
+    unsafe {
+        std::env::set_var("VENTO_TEST_KI_PASSWORD", "hunter2");
+        std::env::set_var("VENTO_TEST_KI_OTP", "123456");
+    }
+    let auth = interactive_auth(
+        r#"
+method: keyboard_interactive
+username: partner
+passwordRef: VENTO_TEST_KI_PASSWORD
+prompts:
+  - pattern: "(?i)verification code"
+    secretRef: VENTO_TEST_KI_OTP
+"#,
+    );
+    let mut responder = PromptResponder::from_auth(&auth).unwrap();
+    let prompts = [
+        Prompt {
+            text: Cow::Borrowed("Password: "),
+            echo: false,
+        },
+        Prompt {
+            text: Cow::Borrowed("Verification code: "),
+            echo: false,
+        },
+    ];
+    let answers = responder.prompt("partner", "", &prompts);
+    assert_eq!(answers, vec!["hunter2".to_string(), "123456".to_string()]);
+}
+
+#[test]
+fn test_prompt_responder_without_match_sends_empty_answer() {
+    let mut responder = PromptResponder::new(
+        vec![(regex::Regex::new("(?i)token").unwrap(), "abc".into())],
+        None,
+    );
+    assert_eq!(responder.respond("Token:"), Some("abc"));
+    let prompts = [Prompt {
+        text: Cow::Borrowed("Password: "),
+        echo: false,
+    }];
+    assert_eq!(responder.prompt("partner", "", &prompts), vec![String::new()]);
+}
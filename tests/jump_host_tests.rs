@@ -0,0 +1,94 @@
+use vento::*;
+
+fn target_auth(method: AuthenticationMethod) -> Authentication {
+    Authentication {
+        method,
+        username: "deploy".into(),
+        password_ref: None,
+        private_key_ref: Some("/home/deploy/.ssh/id_ed25519".into()),
+        passphrase_ref: None,
+        ssh_config_alias: Some("prod-sftp".into()),
+        ssh_config_file: None,
+        prompts: None,
+        host_key_fingerprint: Some("SHA256:target".into()),
+        jump_hosts: None,
+    }
+}
+
+#[test]
+fn test_jump_hosts_yaml_parsing() {
+    let auth: Authentication = serde_yaml::from_str(
+        r#"
+method: private_key
+username: deploy
+privateKeyRef: /home/deploy/.ssh/id_ed25519
+jumpHosts:
+  - host: bastion1.example.com
+  - host: bastion2.internal
+    port: 2222
+    authentication:
+      method: agent
+      username: jump
+"#,
+    )
+    .unwrap();
+    let jumps = auth.jump_hosts.as_ref().unwrap();
+    assert_eq!(jumps.len(), 2);
+    assert_eq!(jumps[1].port, Some(2222));
+    assert!(auth.validate().is_ok());
+}
+
+#[test]
+fn test_jump_host_requires_host() {
+    let mut auth = target_auth(AuthenticationMethod::PrivateKey);
+    auth.jump_hosts = Some(vec![JumpHost {
+        host: " ".into(),
+        port: None,
+        authentication: None,
+    }]);
+    assert!(matches!(auth.validate(), Err(AppError::Validation(msg)) if msg.contains("Jump host")));
+}
+
+#[test]
+fn test_jump_host_reuses_target_authentication() {
+    let target = target_auth(AuthenticationMethod::PrivateKey);
+    let jump = JumpHost {
+        host: "bastion.example.com".into(),
+        port: None,
+        authentication: None,
+    };
+    let auth = jump.effective_authentication(&target);
+    assert_eq!(auth.username, "deploy");
+    assert_eq!(auth.private_key_ref, target.private_key_ref);
+    // 接続先固有の設定は引き継がない
+    // Target specific settings are not carried over
+    assert!(auth.host_key_fingerprint.is_none());
+    assert!(auth.jump_hosts.is_none());
+
+    let target = target_auth(AuthenticationMethod::SshConfig);
+    let auth = jump.effective_authentication(&target);
+    assert_eq!(auth.ssh_config_alias.as_deref(), Some("bastion.example.com"));
+}
+
+#[test]
+fn test_parse_proxy_jump() {
+    let target = target_auth(AuthenticationMethod::SshConfig);
+    let jumps = parse_proxy_jump("ops@bastion1:2022,bastion2,[fd00::1]:22", &target);
+    assert_eq!(jumps.len(), 3);
+
+    assert_eq!(jumps[0].host, "bastion1");
+    assert_eq!(jumps[0].port, Some(2022));
+    let auth = jumps[0].authentication.as_ref().unwrap();
+    assert_eq!(auth.method, AuthenticationMethod::SshConfig);
+    assert_eq!(auth.username, "ops");
+    assert_eq!(auth.ssh_config_alias.as_deref(), Some("bastion1"));
+
+    assert_eq!(jumps[1].host, "bastion2");
+    assert_eq!(jumps[1].port, None);
+    assert_eq!(jumps[1].authentication.as_ref().unwrap().username, "deploy");
+
+    assert_eq!(jumps[2].host, "fd00::1");
+    assert_eq!(jumps[2].port, Some(22));
+
+    assert!(parse_proxy_jump("none", &target).is_empty());
+}
@@ -0,0 +1,117 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use vento::*;
+
+fn lock_context(dir: &Path, lock_wait: Option<Duration>) -> TransferContext {
+    TransferContext::default().with_options(TransferOptions {
+        lock_dir: Some(dir.to_path_buf()),
+        lock_wait,
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_second_lock_fails_fast_with_holder_pid() {
+    let dir = tempfile::tempdir().unwrap();
+    let context = lock_context(dir.path(), None);
+
+    let lock = ProfileLock::acquire("daily-report", &context).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(lock.path()).unwrap().trim(),
+        std::process::id().to_string()
+    );
+
+    let err = ProfileLock::acquire("daily-report", &context)
+        .await
+        .unwrap_err();
+    match err.downcast_ref::<AppError>() {
+        Some(app_error @ AppError::ProfileLocked { profile_id, pid, .. }) => {
+            assert_eq!(profile_id, "daily-report");
+            assert_eq!(*pid, Some(std::process::id()));
+            assert_eq!(app_error.exit_code(), EXIT_PROFILE_LOCKED);
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    // 別のプロファイルは影響を受けない
+    // Other profiles are not affected
+    ProfileLock::acquire("weekly-report", &context).await.unwrap();
+
+    // 解放後は再び取得でき、PIDは消えている
+    // After release it can be taken again, and the PID is cleared
+    let path = lock.path().to_path_buf();
+    drop(lock);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    ProfileLock::acquire("daily-report", &context).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_waits_for_lock_to_be_released() {
+    let dir = tempfile::tempdir().unwrap();
+    let context = lock_context(dir.path(), Some(Duration::from_secs(5)));
+
+    let lock = ProfileLock::acquire("nightly", &context).await.unwrap();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        drop(lock);
+    });
+    ProfileLock::acquire("nightly", &context).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_wait_gives_up_after_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let context = lock_context(dir.path(), Some(Duration::from_millis(600)));
+
+    let _lock = ProfileLock::acquire("nightly", &context).await.unwrap();
+    let err = ProfileLock::acquire("nightly", &context).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AppError>(),
+        Some(AppError::ProfileLocked { .. })
+    ));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_stale_lock_from_dead_process_is_taken_over() {
+    let dir = tempfile::tempdir().unwrap();
+    let context = lock_context(dir.path(), None);
+
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let dead_pid = child.id();
+    child.wait().unwrap();
+    assert!(!process_alive(dead_pid));
+    assert!(process_alive(std::process::id()));
+
+    let path = ProfileLock::path_for(dir.path(), "crashed");
+    fs::write(&path, format!("{}\n", dead_pid)).unwrap();
+
+    let lock = ProfileLock::acquire("crashed", &context).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(lock.path()).unwrap().trim(),
+        std::process::id().to_string()
+    );
+}
+
+#[test]
+fn test_lock_file_name_is_sanitized() {
+    let path = ProfileLock::path_for(Path::new("/run/vento"), "../team a/report");
+    assert_eq!(path, Path::new("/run/vento/.._team_a_report.lock"));
+}
+
+#[test]
+fn test_lock_settings_from_app_config() {
+    let app_config: AppConfig = serde_yaml::from_str(
+        r#"
+lockDir: /var/lock/vento
+lockWaitSecs: 30
+"#,
+    )
+    .unwrap();
+    let options = TransferOptions::from_app_config(&app_config);
+    assert!(options.profile_lock);
+    assert_eq!(options.lock_dir.as_deref(), Some(Path::new("/var/lock/vento")));
+    assert_eq!(options.lock_wait, Some(Duration::from_secs(30)));
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use vento::*;
+
+fn local_source(path: &str, filter: Option<FileFilter>) -> Source {
+    Source {
+        kind: SourceType::Local,
+        path: path.into(),
+        host: None,
+        port: None,
+        authentication: None,
+        trigger: Trigger {
+            kind: TriggerType::Manual,
+            schedule: None,
+            missed_runs: None,
+            overlap: None,
+            debounce_ms: None,
+        },
+        filter,
+        on_success: None,
+        stability: None,
+    }
+}
+
+#[test]
+fn test_glob_in_source_path() {
+    let source = local_source("/out/*.csv", None);
+    let selection = FileSelection::from_source(&source).unwrap().unwrap();
+
+    assert_eq!(selection.root, Path::new("/out"));
+    assert!(selection.matcher.is_match(Path::new("report.csv")));
+    assert!(!selection.matcher.is_match(Path::new("report.txt")));
+}
+
+#[test]
+fn test_regex_include_and_exclude() {
+    let filter = FileFilter {
+        include: Some(vec![r"^report_\d{8}\.txt$".into()]),
+        exclude: Some(vec![r"^report_20250101\.txt$".into()]),
+        syntax: Some(PatternSyntax::Regex),
+    };
+    let source = local_source("/out", Some(filter));
+    let selection = FileSelection::from_source(&source).unwrap().unwrap();
+
+    assert!(selection.matcher.is_match(Path::new("report_20250630.txt")));
+    assert!(!selection.matcher.is_match(Path::new("report_20250101.txt")));
+    assert!(!selection.matcher.is_match(Path::new("report_latest.txt")));
+}
+
+#[test]
+fn test_literal_source_path_has_no_selection() {
+    let source = local_source("/out/report.csv", None);
+    assert!(FileSelection::from_source(&source).unwrap().is_none());
+}
+
+#[test]
+fn test_invalid_regex_is_validation_error() {
+    let filter = FileFilter {
+        include: Some(vec!["(unclosed".into()]),
+        exclude: None,
+        syntax: Some(PatternSyntax::Regex),
+    };
+    let source = local_source("/out", Some(filter));
+    let result = source.validate();
+    assert!(matches!(result, Err(AppError::Validation(msg)) if msg.contains("(unclosed")));
+}
+
+#[test]
+fn test_glob_to_regex() {
+    assert_eq!(glob_to_regex("*.csv"), r"^[^/]*\.csv$");
+    assert_eq!(glob_to_regex("data_??.[!t]xt"), r"^data_[^/][^/]\.[^t]xt$");
+}
@@ -57,11 +57,22 @@ fn test_invalid_sftp_source_missing_host() {
             password_ref: Some("ref".into()),
             private_key_ref: None,
             ssh_config_alias: None,
+            ssh_config_file: None,
+            passphrase_ref: None,
+            prompts: None,
+            host_key_fingerprint: None,
+            jump_hosts: None,
         }),
         trigger: Trigger {
             kind: TriggerType::Manual,
             schedule: None,
+            missed_runs: None,
+            overlap: None,
+            debounce_ms: None,
         },
+        filter: None,
+        on_success: None,
+        stability: None,
     };
 
     let result = source.validate();
@@ -75,6 +86,9 @@ fn test_trigger_schedule_missing_schedule() {
     let trigger = Trigger {
         kind: TriggerType::Schedule,
         schedule: None,
+        missed_runs: None,
+        overlap: None,
+        debounce_ms: None,
     };
 
     let result = trigger.validate();
@@ -88,6 +102,9 @@ fn test_trigger_schedule_invalid_cron() {
     let trigger = Trigger {
         kind: TriggerType::Schedule,
         schedule: Some("invalid_cron".into()),
+        missed_runs: None,
+        overlap: None,
+        debounce_ms: None,
     };
 
     let result = trigger.validate();
@@ -104,6 +121,11 @@ fn test_authentication_missing_private_key_ref() {
         password_ref: None,
         private_key_ref: None,
         ssh_config_alias: None,
+        ssh_config_file: None,
+        passphrase_ref: None,
+        prompts: None,
+        host_key_fingerprint: None,
+        jump_hosts: None,
     };
 
     let result = auth.validate();
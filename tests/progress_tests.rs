@@ -0,0 +1,72 @@
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use vento::*;
+
+fn recording_context() -> (TransferContext, Arc<Mutex<Vec<ProgressEvent>>>) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&events);
+    let context = TransferContext::default().with_progress(Arc::new(move |event: &ProgressEvent| {
+        recorded.lock().unwrap().push(event.clone());
+    }));
+    (context, events)
+}
+
+#[test]
+fn test_progress_reader_reports_start_and_completion() {
+    let (context, events) = recording_context();
+    let data = vec![7u8; 64 * 1024];
+    let mut reader = ProgressReader::new(
+        Cursor::new(data.clone()),
+        &context,
+        Path::new("/out/data.bin"),
+        0,
+        data.len() as u64,
+    );
+    let copied = io::copy(&mut reader, &mut io::sink()).unwrap();
+    assert_eq!(copied, data.len() as u64);
+
+    let events = events.lock().unwrap();
+    assert!(events.len() >= 2);
+    assert!(!events[0].finished);
+    let last = events.last().unwrap();
+    assert!(last.finished);
+    assert_eq!(last.file, Path::new("/out/data.bin"));
+    assert_eq!(last.bytes_done, data.len() as u64);
+    assert_eq!(last.total_bytes, data.len() as u64);
+    assert_eq!(last.percent(), 100.0);
+    assert_eq!(events.iter().filter(|event| event.finished).count(), 1);
+}
+
+#[test]
+fn test_progress_reader_counts_resumed_offset_as_done() {
+    let (context, events) = recording_context();
+    let mut reader = ProgressReader::new(
+        Cursor::new(vec![0u8; 100]),
+        &context,
+        Path::new("partial.bin"),
+        300,
+        400,
+    );
+    let mut buf = [0u8; 50];
+    reader.read_exact(&mut buf).unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(events[0].bytes_done, 350);
+    assert_eq!(events[0].percent(), 87.5);
+    assert!(!events[0].finished);
+}
+
+#[test]
+fn test_progress_reader_reports_empty_file_as_finished() {
+    let (context, events) = recording_context();
+    let mut reader = ProgressReader::new(io::empty(), &context, Path::new("empty.txt"), 0, 0);
+    io::copy(&mut reader, &mut io::sink()).unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].finished);
+    assert_eq!(events[0].percent(), 100.0);
+}
+
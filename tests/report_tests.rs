@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use vento::*;
+
+fn hook_profile(pre_transfer_command: &str) -> TransferProfile {
+    serde_yaml::from_str(&format!(
+        r#"
+profileId: report-test
+source:
+  type: local
+  path: /tmp/vento-report-test
+  trigger:
+    type: manual
+destination:
+  type: sftp
+  path: /upload
+  host: example.com
+  port: 22
+  authentication:
+    method: password
+    username: user
+    passwordRef: VENTO_REPORT_TEST_PASSWORD
+transferProtocol:
+  protocol: SFTP
+preTransferCommand: "{}"
+"#,
+        pre_transfer_command
+    ))
+    .unwrap()
+}
+
+#[cfg(not(target_os = "windows"))]
+#[tokio::test]
+async fn test_failed_run_attaches_report_with_hook_exit_code() {
+    let err = process_transfer_profile(hook_profile("exit 3"))
+        .await
+        .unwrap_err();
+
+    let report = err.downcast_ref::<TransferReport>().unwrap();
+    assert_eq!(report.profile_id, "report-test");
+    assert_eq!(report.status, TransferStatus::Failed);
+    assert_eq!(report.attempts, 0);
+    assert!(report.finished_at.is_some());
+    assert_eq!(
+        report.hooks,
+        vec![HookReport {
+            stage: "pre-transfer".into(),
+            command: "exit 3".into(),
+            exit_code: Some(3),
+            success: false,
+        }]
+    );
+    // 元のエラーもそのまま取り出せる
+    // The original error is still available
+    assert!(matches!(
+        err.downcast_ref::<AppError>(),
+        Some(AppError::CommandFailed { code: Some(3), .. })
+    ));
+}
+
+#[test]
+fn test_report_records_outcome_and_serializes_to_json() {
+    let mut report = TransferReport::start("nightly");
+    report.attempts = 2;
+    report.record_outcome(&TransferOutcome::new(
+        vec![TransferredFile {
+            source: PathBuf::from("/out/a.csv"),
+            destination: PathBuf::from("/in/a.csv"),
+            relative: PathBuf::from("a.csv"),
+            size: 2048,
+            bytes: 2048,
+            checksum: Some("abc123".into()),
+        }],
+        Duration::from_secs(2),
+    ));
+    report.finish(None);
+
+    assert_eq!(report.status, TransferStatus::Succeeded);
+    assert_eq!(report.bytes, 2048);
+    assert_eq!(report.bytes_per_sec, 1024.0);
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+    assert_eq!(json["profileId"], "nightly");
+    assert_eq!(json["status"], "succeeded");
+    assert_eq!(json["attempts"], 2);
+    assert_eq!(json["files"][0]["size"], 2048);
+    assert_eq!(json["files"][0]["checksum"], "abc123");
+    assert!(json["startedAt"].is_string());
+    assert!(json["error"].is_null());
+}
+
+#[test]
+fn test_report_marks_cancelled_runs() {
+    let mut report = TransferReport::start("nightly");
+    report.finish(Some(&AppError::Cancelled.into()));
+    assert_eq!(report.status, TransferStatus::Cancelled);
+    assert_eq!(report.error.as_deref(), Some("Transfer was cancelled"));
+}
@@ -0,0 +1,73 @@
+use std::io::Cursor;
+
+use vento::{resume_offset, RESUME_VERIFY_BYTES};
+
+fn source_bytes(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn test_resume_offset_matching_tail() {
+    let source = source_bytes(200_000);
+    let partial = source[..150_000].to_vec();
+
+    let offset = resume_offset(
+        &mut Cursor::new(&partial),
+        partial.len() as u64,
+        &mut Cursor::new(&source),
+        source.len() as u64,
+    )
+    .unwrap();
+    assert_eq!(offset, 150_000);
+}
+
+#[test]
+fn test_resume_offset_mismatching_tail_restarts() {
+    let source = source_bytes(200_000);
+    let mut partial = source[..150_000].to_vec();
+    let last = partial.len() - 1;
+    partial[last] ^= 0xff;
+
+    let offset = resume_offset(
+        &mut Cursor::new(&partial),
+        partial.len() as u64,
+        &mut Cursor::new(&source),
+        source.len() as u64,
+    )
+    .unwrap();
+    assert_eq!(offset, 0);
+}
+
+#[test]
+fn test_resume_offset_only_checks_tail() {
+    let source = source_bytes(200_000);
+    let mut partial = source[..150_000].to_vec();
+    // 検証範囲より前の破損は検出対象外
+    // Corruption before the verified tail is out of scope for the resume check
+    partial[0] ^= 0xff;
+    assert!(partial.len() as u64 > RESUME_VERIFY_BYTES);
+
+    let offset = resume_offset(
+        &mut Cursor::new(&partial),
+        partial.len() as u64,
+        &mut Cursor::new(&source),
+        source.len() as u64,
+    )
+    .unwrap();
+    assert_eq!(offset, 150_000);
+}
+
+#[test]
+fn test_resume_offset_larger_destination_restarts() {
+    let source = source_bytes(1_000);
+    let partial = source_bytes(2_000);
+
+    let offset = resume_offset(
+        &mut Cursor::new(&partial),
+        partial.len() as u64,
+        &mut Cursor::new(&source),
+        source.len() as u64,
+    )
+    .unwrap();
+    assert_eq!(offset, 0);
+}
@@ -0,0 +1,199 @@
+use std::cell::Cell;
+use std::io;
+use std::time::Duration;
+
+use vento::*;
+
+fn retry_profile(retry: RetryPolicy) -> TransferProfile {
+    TransferProfile {
+        retry: Some(retry),
+        ..Default::default()
+    }
+}
+
+fn no_delay(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: Some(max_attempts),
+        initial_delay_ms: Some(0),
+        jitter: Some(0.0),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_retry_yaml_parsing() {
+    let retry: RetryPolicy = serde_yaml::from_str(
+        r#"
+maxAttempts: 5
+initialDelayMs: 500
+backoffFactor: 3.0
+jitter: 0.2
+retryOn: [connect, auth, sizeLimit]
+"#,
+    )
+    .unwrap();
+    assert_eq!(retry.max_attempts(), 5);
+    assert!(retry.is_retryable(ErrorClass::Auth));
+    assert!(retry.is_retryable(ErrorClass::SizeLimit));
+    assert!(!retry.is_retryable(ErrorClass::Io));
+}
+
+#[test]
+fn test_retry_default_classes() {
+    let retry = RetryPolicy::default();
+    assert_eq!(retry.max_attempts(), DEFAULT_MAX_ATTEMPTS);
+    assert!(retry.is_retryable(ErrorClass::Connect));
+    assert!(retry.is_retryable(ErrorClass::Io));
+    assert!(!retry.is_retryable(ErrorClass::Auth));
+    assert!(!retry.is_retryable(ErrorClass::Validation));
+    assert!(!retry.is_retryable(ErrorClass::SizeLimit));
+}
+
+#[test]
+fn test_retry_backoff_grows_and_is_capped() {
+    let retry = RetryPolicy {
+        initial_delay_ms: Some(100),
+        backoff_factor: Some(2.0),
+        max_delay_ms: Some(500),
+        ..Default::default()
+    };
+    assert_eq!(retry.base_delay(1), Duration::from_millis(100));
+    assert_eq!(retry.base_delay(2), Duration::from_millis(200));
+    assert_eq!(retry.base_delay(3), Duration::from_millis(400));
+    assert_eq!(retry.base_delay(4), Duration::from_millis(500));
+}
+
+#[test]
+fn test_retry_jitter_stays_in_range() {
+    let retry = RetryPolicy {
+        initial_delay_ms: Some(1000),
+        jitter: Some(0.5),
+        ..Default::default()
+    };
+    for _ in 0..100 {
+        let delay = retry.delay(1);
+        assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1500));
+    }
+}
+
+#[test]
+fn test_retry_validation() {
+    assert!(no_delay(0).validate().is_err());
+    assert!(
+        RetryPolicy {
+            backoff_factor: Some(0.5),
+            ..Default::default()
+        }
+        .validate()
+        .is_err()
+    );
+    assert!(
+        RetryPolicy {
+            jitter: Some(1.5),
+            ..Default::default()
+        }
+        .validate()
+        .is_err()
+    );
+    assert!(no_delay(3).validate().is_ok());
+}
+
+#[test]
+fn test_classify_error() {
+    let refused: anyhow::Error = io::Error::from(io::ErrorKind::ConnectionRefused).into();
+    assert_eq!(
+        classify_error(&refused.context("Failed to connect to example:22")),
+        ErrorClass::Connect
+    );
+
+    let io_error: anyhow::Error = io::Error::from(io::ErrorKind::PermissionDenied).into();
+    assert_eq!(classify_error(&io_error), ErrorClass::Io);
+
+    let auth: anyhow::Error = AppError::AuthenticationFailed("denied".into()).into();
+    assert_eq!(classify_error(&auth), ErrorClass::Auth);
+
+    let too_large: anyhow::Error = AppError::FileTooLarge {
+        path: "/data/big.bin".into(),
+        limit_mb: 10,
+    }
+    .into();
+    assert_eq!(classify_error(&too_large), ErrorClass::SizeLimit);
+
+    let validation: anyhow::Error = AppError::Validation("bad".into()).into();
+    assert_eq!(classify_error(&validation), ErrorClass::Validation);
+
+    assert_eq!(classify_error(&anyhow::anyhow!("unknown")), ErrorClass::Other);
+}
+
+#[tokio::test]
+async fn test_with_retry_retries_transient_errors() {
+    let profile = retry_profile(no_delay(3));
+    let attempts = Cell::new(0);
+    let result = with_retry(&profile, &CancellationToken::new(), || {
+        attempts.set(attempts.get() + 1);
+        let attempt = attempts.get();
+        async move {
+            if attempt < 3 {
+                Err(io::Error::from(io::ErrorKind::ConnectionReset).into())
+            } else {
+                Ok(attempt)
+            }
+        }
+    })
+    .await;
+    assert_eq!(result.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn test_with_retry_gives_up_after_max_attempts() {
+    let profile = retry_profile(no_delay(2));
+    let attempts = Cell::new(0);
+    let result: anyhow::Result<()> = with_retry(&profile, &CancellationToken::new(), || {
+        attempts.set(attempts.get() + 1);
+        async { Err(io::Error::from(io::ErrorKind::TimedOut).into()) }
+    })
+    .await;
+    assert!(result.is_err());
+    assert_eq!(attempts.get(), 2);
+}
+
+#[tokio::test]
+async fn test_with_retry_stops_on_non_retryable_error() {
+    let profile = retry_profile(no_delay(5));
+    let attempts = Cell::new(0);
+    let result: anyhow::Result<()> = with_retry(&profile, &CancellationToken::new(), || {
+        attempts.set(attempts.get() + 1);
+        async { Err(AppError::Validation("bad".into()).into()) }
+    })
+    .await;
+    assert!(result.is_err());
+    assert_eq!(attempts.get(), 1);
+}
+
+#[tokio::test]
+async fn test_with_retry_without_policy_runs_once() {
+    let profile = TransferProfile::default();
+    let attempts = Cell::new(0);
+    let result: anyhow::Result<()> = with_retry(&profile, &CancellationToken::new(), || {
+        attempts.set(attempts.get() + 1);
+        async { Err(io::Error::from(io::ErrorKind::ConnectionReset).into()) }
+    })
+    .await;
+    assert!(result.is_err());
+    assert_eq!(attempts.get(), 1);
+}
+
+#[tokio::test]
+async fn test_with_retry_stops_when_cancelled() {
+    let profile = retry_profile(no_delay(5));
+    let cancel = CancellationToken::new();
+    let attempts = Cell::new(0);
+    let result: anyhow::Result<()> = with_retry(&profile, &cancel, || {
+        attempts.set(attempts.get() + 1);
+        cancel.cancel();
+        async { Err(io::Error::from(io::ErrorKind::ConnectionReset).into()) }
+    })
+    .await;
+    assert!(result.is_err());
+    assert_eq!(attempts.get(), 1);
+}
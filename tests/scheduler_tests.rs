@@ -0,0 +1,186 @@
+use chrono::{DateTime, Local, TimeZone};
+use tempfile::tempdir;
+use vento::*;
+
+fn scheduled_profile(profile_id: &str, schedule: Option<&str>) -> TransferProfile {
+    let mut profile = TransferProfile {
+        profile_id: profile_id.to_string(),
+        ..Default::default()
+    };
+    profile.source.trigger = Trigger {
+        kind: if schedule.is_some() {
+            TriggerType::Schedule
+        } else {
+            TriggerType::Manual
+        },
+        schedule: schedule.map(str::to_string),
+        missed_runs: None,
+        overlap: None,
+        debounce_ms: None,
+    };
+    profile
+}
+
+#[test]
+fn test_scheduler_collects_scheduled_profiles_only() {
+    let now = Local.with_ymd_and_hms(2026, 3, 2, 8, 30, 0).unwrap();
+    let scheduler = Scheduler::new(
+        &[
+            scheduled_profile("manual", None),
+            scheduled_profile("hourly", Some("0 0 * * * *")),
+            scheduled_profile("broken", Some("not a cron")),
+            scheduled_profile("nightly", Some("0 0 2 * * *")),
+        ],
+        now,
+        &ScheduleState::default(),
+    );
+
+    let ids: Vec<_> = scheduler
+        .entries()
+        .iter()
+        .map(|entry| entry.profile_id.as_str())
+        .collect();
+    assert_eq!(ids, ["hourly", "nightly"]);
+    assert_eq!(
+        scheduler.next_run(),
+        Some(Local.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap())
+    );
+}
+
+#[test]
+fn test_take_due_advances_next_run() {
+    let start = Local.with_ymd_and_hms(2026, 3, 2, 8, 30, 0).unwrap();
+    let mut scheduler = Scheduler::new(
+        &[
+            scheduled_profile("hourly", Some("0 0 * * * *")),
+            scheduled_profile("nightly", Some("0 0 2 * * *")),
+        ],
+        start,
+        &ScheduleState::default(),
+    );
+
+    assert!(scheduler.take_due(start).is_empty());
+
+    let nine = Local.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap();
+    let due = scheduler.take_due(nine);
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].profile_id, "hourly");
+    assert_eq!(due[0].run_times, [nine]);
+    assert_eq!(due[0].plan(nine), RunPlan { runs: 1, missed: 0 });
+    assert_eq!(
+        scheduler.next_run(),
+        Some(Local.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap())
+    );
+    // 同じ時刻で二重に実行しない
+    // The same run time does not fire twice
+    assert!(scheduler.take_due(nine).is_empty());
+}
+
+#[test]
+fn test_trigger_cron_schedule() {
+    let trigger = Trigger {
+        kind: TriggerType::Schedule,
+        schedule: None,
+        missed_runs: None,
+        overlap: None,
+        debounce_ms: None,
+    };
+    assert!(matches!(trigger.cron_schedule(), Err(AppError::MissingSchedule)));
+
+    let manual = Trigger {
+        kind: TriggerType::Manual,
+        schedule: Some("0 0 * * * *".into()),
+        missed_runs: None,
+        overlap: None,
+        debounce_ms: None,
+    };
+    assert!(manual.cron_schedule().unwrap().is_none());
+}
+
+fn at(hour: u32, minute: u32) -> DateTime<Local> {
+    Local.with_ymd_and_hms(2026, 3, 2, hour, minute, 0).unwrap()
+}
+
+fn due_run(missed_runs: MissedRunPolicy, run_times: Vec<DateTime<Local>>) -> DueRun {
+    DueRun {
+        profile_id: "hourly".into(),
+        missed_runs,
+        overlap: OverlapPolicy::Skip,
+        run_times,
+    }
+}
+
+#[test]
+fn test_missed_runs_are_found_from_the_state() {
+    let mut state = ScheduleState::default();
+    state.record("hourly", at(5, 0));
+    let now = at(8, 30);
+    let mut scheduler = Scheduler::new(&[scheduled_profile("hourly", Some("0 0 * * * *"))], now, &state);
+
+    let due = scheduler.take_due(now);
+    assert_eq!(due[0].run_times, [at(6, 0), at(7, 0), at(8, 0)]);
+    assert_eq!(due[0].last_run_time(), Some(at(8, 0)));
+    assert_eq!(scheduler.next_run(), Some(at(9, 0)));
+}
+
+#[test]
+fn test_missed_run_policies() {
+    let now = at(8, 0);
+    let missed_only = vec![at(6, 0), at(7, 0)];
+    let with_on_time = vec![at(6, 0), at(7, 0), at(8, 0)];
+
+    assert_eq!(
+        due_run(MissedRunPolicy::Skip, missed_only.clone()).plan(now),
+        RunPlan { runs: 0, missed: 2 }
+    );
+    assert_eq!(
+        due_run(MissedRunPolicy::Skip, with_on_time.clone()).plan(now),
+        RunPlan { runs: 1, missed: 2 }
+    );
+    assert_eq!(
+        due_run(MissedRunPolicy::RunOnce, missed_only.clone()).plan(now),
+        RunPlan { runs: 1, missed: 2 }
+    );
+    assert_eq!(
+        due_run(MissedRunPolicy::RunOnce, with_on_time.clone()).plan(now),
+        RunPlan { runs: 1, missed: 2 }
+    );
+    assert_eq!(
+        due_run(MissedRunPolicy::RunAll, missed_only).plan(now),
+        RunPlan { runs: 2, missed: 2 }
+    );
+    assert_eq!(
+        due_run(MissedRunPolicy::RunAll, with_on_time).plan(now),
+        RunPlan { runs: 3, missed: 2 }
+    );
+}
+
+#[test]
+fn test_trigger_policies_deserialize() {
+    let trigger: Trigger = serde_yaml::from_str(
+        r#"
+type: schedule
+schedule: "0 0 * * * *"
+missedRuns: run-once
+overlap: queue
+"#,
+    )
+    .unwrap();
+    assert_eq!(trigger.missed_runs, Some(MissedRunPolicy::RunOnce));
+    assert_eq!(trigger.overlap, Some(OverlapPolicy::Queue));
+    assert!(serde_yaml::from_str::<Trigger>("type: schedule\noverlap: later\n").is_err());
+}
+
+#[test]
+fn test_schedule_state_roundtrip() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("state/schedule_state.json");
+    assert_eq!(ScheduleState::load(&path).unwrap(), ScheduleState::default());
+
+    let mut state = ScheduleState::default();
+    state.record("hourly", at(8, 0));
+    state.save(&path).unwrap();
+    let loaded = ScheduleState::load(&path).unwrap();
+    assert_eq!(loaded.last_run("hourly"), Some(at(8, 0)));
+    assert_eq!(loaded.last_run("nightly"), None);
+}
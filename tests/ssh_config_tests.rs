@@ -0,0 +1,189 @@
+use std::fs;
+use tempfile::tempdir;
+use vento::*;
+
+const CONFIG: &str = r#"
+Host prod-sftp
+    HostName sftp.example.com
+    Port 2222
+    User deploy
+    IdentityFile /keys/first_key
+    IdentityFile /keys/second_key
+    IdentitiesOnly yes
+    ConnectTimeout 7
+    ServerAliveInterval 15
+    Ciphers aes256-ctr,aes128-ctr
+    KexAlgorithms curve25519-sha256
+    UserKnownHostsFile ~/.ssh/vento_known_hosts
+    StrictHostKeyChecking accept-new
+    ProxyJump ops@bastion:2022
+
+Host *
+    User fallback
+    ForwardAgent no
+"#;
+
+#[test]
+fn test_ssh_config_collects_identity_files_from_every_matching_block() {
+    let config = parse_ssh_config(
+        r#"
+Host prod-*
+    IdentityFile=/keys/prod_key
+
+Host prod-sftp
+    IdentityFile "/keys/with space"
+    IdentityFile /keys/prod_key
+
+Host *
+    IdentityFile /keys/common_key
+"#,
+    )
+    .unwrap();
+    let settings = SshConfigSettings::resolve(&config, "prod-sftp");
+    assert_eq!(
+        settings.identity_files,
+        vec![
+            std::path::PathBuf::from("/keys/prod_key"),
+            std::path::PathBuf::from("/keys/with space"),
+            std::path::PathBuf::from("/keys/common_key")
+        ]
+    );
+}
+
+#[test]
+fn test_ssh_config_resolves_all_supported_options() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("config");
+    fs::write(&path, CONFIG).unwrap();
+
+    let config = load_ssh_config(Some(path.to_str().unwrap())).unwrap();
+    let settings = SshConfigSettings::resolve(&config, "prod-sftp");
+
+    assert_eq!(settings.host_name.as_deref(), Some("sftp.example.com"));
+    assert_eq!(settings.port, Some(2222));
+    assert_eq!(settings.user.as_deref(), Some("deploy"));
+    // すべてのIdentityFileを記述順に保持する
+    // Every IdentityFile is kept, in order
+    assert_eq!(
+        settings.identity_files,
+        vec![
+            std::path::PathBuf::from("/keys/first_key"),
+            std::path::PathBuf::from("/keys/second_key")
+        ]
+    );
+    assert!(settings.identities_only);
+    assert_eq!(settings.timeouts.connect_secs, Some(7));
+    assert_eq!(settings.timeouts.keepalive_secs, Some(15));
+    assert_eq!(settings.ciphers.as_deref(), Some("aes256-ctr,aes128-ctr"));
+    assert_eq!(settings.kex_algorithms.as_deref(), Some("curve25519-sha256"));
+    assert!(settings.macs.is_none());
+    assert_eq!(settings.host_keys.policy, Some(HostKeyPolicy::AcceptNew));
+    assert_eq!(
+        settings.host_keys.known_hosts_file.as_deref(),
+        Some("~/.ssh/vento_known_hosts")
+    );
+    assert_eq!(settings.proxy_jump.as_deref(), Some("ops@bastion:2022"));
+}
+
+#[test]
+fn test_ssh_config_defaults_for_unmatched_host() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("config");
+    fs::write(&path, CONFIG).unwrap();
+
+    let config = load_ssh_config(Some(path.to_str().unwrap())).unwrap();
+    let settings = SshConfigSettings::resolve(&config, "other");
+
+    assert_eq!(settings.user.as_deref(), Some("fallback"));
+    assert!(settings.host_name.is_none());
+    assert!(!settings.identities_only);
+    assert!(settings.ciphers.is_none());
+    assert!(settings.host_keys.policy.is_none());
+    assert!(settings.proxy_jump.is_none());
+}
+
+#[test]
+fn test_ssh_config_settings_yield_to_profile() {
+    let profile = TimeoutSettings {
+        connect_secs: Some(3),
+        ..Default::default()
+    };
+    let from_config = TimeoutSettings {
+        connect_secs: Some(7),
+        keepalive_secs: Some(15),
+        ..Default::default()
+    };
+    let merged = profile.or(&from_config);
+    assert_eq!(merged.connect_secs, Some(3));
+    assert_eq!(merged.keepalive_secs, Some(15));
+}
+
+#[test]
+fn test_ssh_config_file_must_exist() {
+    let auth: Authentication = serde_yaml::from_str(
+        r#"
+method: ssh_config
+username: deploy
+sshConfigAlias: prod-sftp
+sshConfigFile: /nonexistent/vento/ssh_config
+"#,
+    )
+    .unwrap();
+    assert!(matches!(auth.validate(), Err(AppError::Validation(msg)) if msg.contains("does not exist")));
+    assert!(load_ssh_config(auth.ssh_config_file.as_deref()).is_err());
+}
+
+#[test]
+fn test_ssh_config_values_sit_between_profile_and_app_config() {
+    let config = parse_ssh_config(CONFIG).unwrap();
+    let settings = SshConfigSettings::resolve(&config, "prod-sftp");
+    let connection = ConnectionSettings {
+        timeouts: TimeoutSettings {
+            handshake_secs: Some(20),
+            ..Default::default()
+        },
+        default_timeouts: TimeoutSettings {
+            connect_secs: Some(60),
+            keepalive_secs: Some(90),
+            operation_secs: Some(300),
+            ..Default::default()
+        },
+        default_host_keys: HostKeySettings {
+            policy: Some(HostKeyPolicy::Strict),
+            known_hosts_file: Some("/etc/vento/known_hosts".to_string()),
+        },
+        ..Default::default()
+    };
+
+    // ホスト固有のssh_configの値はconfig.yamlの値より優先する
+    // Host-specific ssh_config values win over config.yaml
+    let (timeouts, host_keys) = connection.resolve(Some(&settings));
+    assert_eq!(timeouts.handshake_secs, Some(20));
+    assert_eq!(timeouts.connect_secs, Some(7));
+    assert_eq!(timeouts.keepalive_secs, Some(15));
+    assert_eq!(timeouts.operation_secs, Some(300));
+    assert_eq!(host_keys.policy, Some(HostKeyPolicy::AcceptNew));
+    assert_eq!(
+        host_keys.known_hosts_file.as_deref(),
+        Some("~/.ssh/vento_known_hosts")
+    );
+
+    let (timeouts, host_keys) = connection.resolve(None);
+    assert_eq!(timeouts.connect_secs, Some(60));
+    assert_eq!(host_keys.policy, Some(HostKeyPolicy::Strict));
+}
+
+#[test]
+fn test_ssh_config_port_fills_in_when_no_port_is_given() {
+    let config = parse_ssh_config(CONFIG).unwrap();
+    let settings = SshConfigSettings::resolve(&config, "prod-sftp");
+    // プロファイルでポートを省略した場合はssh_configの `Port` を使う
+    // ssh_config's `Port` is used when the profile omits the port
+    assert_eq!(settings.port_or(None), 2222);
+    // 明示されたポート（ProxyJumpの `host:port` など）が優先する
+    // An explicit port (such as a ProxyJump `host:port`) wins
+    assert_eq!(settings.port_or(Some(2022)), 2022);
+
+    let other = SshConfigSettings::resolve(&config, "other");
+    assert_eq!(other.port_or(None), 22);
+}
@@ -12,6 +12,11 @@ fn test_get_private_key_path_envkey_success() {
         password_ref: None,
         private_key_ref: Some("MY_KEY_PATH".into()),
         ssh_config_alias: None,
+        ssh_config_file: None,
+        passphrase_ref: None,
+        prompts: None,
+        host_key_fingerprint: None,
+        jump_hosts: None,
     };
     let path = get_private_key_path(&auth).unwrap();
     assert_eq!(path, "/home/user/.ssh/id_rsa");
@@ -28,6 +33,11 @@ fn test_get_private_key_path_envkey_missing_env() {
         password_ref: None,
         private_key_ref: Some("MISSING_KEY".into()),
         ssh_config_alias: None,
+        ssh_config_file: None,
+        passphrase_ref: None,
+        prompts: None,
+        host_key_fingerprint: None,
+        jump_hosts: None,
     };
     let err = get_private_key_path(&auth).unwrap_err();
 
@@ -46,6 +56,11 @@ fn test_get_private_key_path_private_key_success() {
         password_ref: None,
         private_key_ref: Some("/path/to/key".into()),
         ssh_config_alias: None,
+        ssh_config_file: None,
+        passphrase_ref: None,
+        prompts: None,
+        host_key_fingerprint: None,
+        jump_hosts: None,
     };
     let path = get_private_key_path(&auth).unwrap();
     assert_eq!(path, "/path/to/key");
@@ -59,6 +74,11 @@ fn test_get_private_key_path_missing_key_ref() {
         password_ref: None,
         private_key_ref: None,
         ssh_config_alias: None,
+        ssh_config_file: None,
+        passphrase_ref: None,
+        prompts: None,
+        host_key_fingerprint: None,
+        jump_hosts: None,
     };
     let err = get_private_key_path(&auth).unwrap_err();
     assert!(matches!(err, AppError::MissingPrivateKeyReference));
@@ -72,6 +92,11 @@ fn test_get_private_key_path_unsupported_method() {
         password_ref: None,
         private_key_ref: Some("dummy".into()),
         ssh_config_alias: None,
+        ssh_config_file: None,
+        passphrase_ref: None,
+        prompts: None,
+        host_key_fingerprint: None,
+        jump_hosts: None,
     };
     let err = get_private_key_path(&auth).unwrap_err();
     assert!(matches!(err, AppError::AuthenticationFailed(_)));
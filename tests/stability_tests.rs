@@ -0,0 +1,213 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tempfile::tempdir;
+
+use vento::*;
+
+fn stable_profile(root: &Path, stability: Stability) -> TransferProfile {
+    let mut profile = TransferProfile::default();
+    profile.source.path = root.to_string_lossy().to_string();
+    profile.source.stability = Some(stability);
+    profile
+}
+
+fn trigger_stability(delete_trigger_file: Option<bool>) -> Stability {
+    Stability {
+        trigger_file_suffix: Some(".ok".to_string()),
+        delete_trigger_file,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_stability_yaml_and_validation() {
+    let source: Source = serde_yaml::from_str(
+        r#"
+type: local
+path: /out/*.csv
+trigger:
+  type: manual
+stability:
+  stableSecs: 10
+  maxWaitSecs: 60
+  triggerFileSuffix: .ok
+  deleteTriggerFile: true
+"#,
+    )
+    .unwrap();
+    let stability = source.stability.unwrap();
+    assert_eq!(stability.stable_for(), Some(Duration::from_secs(10)));
+    assert_eq!(stability.max_wait(), Duration::from_secs(60));
+    assert!(stability.deletes_trigger_file());
+    assert_eq!(
+        stability.trigger_file(Path::new("/out/report.csv")),
+        Some(PathBuf::from("/out/report.csv.ok"))
+    );
+    assert!(stability.is_trigger_file(Path::new("report.csv.ok")));
+    assert!(!stability.is_trigger_file(Path::new("report.csv")));
+
+    let defaults = Stability::default();
+    assert_eq!(defaults.stable_for(), None);
+    assert_eq!(
+        defaults.max_wait(),
+        Duration::from_secs(DEFAULT_STABILITY_MAX_WAIT_SECS)
+    );
+
+    let empty_suffix = Stability {
+        trigger_file_suffix: Some(String::new()),
+        ..Default::default()
+    };
+    assert!(matches!(empty_suffix.validate(), Err(AppError::Validation(_))));
+}
+
+#[test]
+fn test_max_wait_shorter_than_stable_time_is_rejected() {
+    let stability = Stability {
+        stable_secs: Some(60),
+        max_wait_secs: Some(30),
+        ..Default::default()
+    };
+    assert!(matches!(
+        stability.validate(),
+        Err(AppError::Validation(message)) if message.contains("maxWaitSecs")
+    ));
+
+    // 既定の上限（300秒）より長い安定時間も拒否する
+    // A stable time beyond the default cap (300s) is rejected too
+    let beyond_default = Stability {
+        stable_secs: Some(DEFAULT_STABILITY_MAX_WAIT_SECS + 1),
+        ..Default::default()
+    };
+    assert!(beyond_default.validate().is_err());
+
+    let equal = Stability {
+        stable_secs: Some(30),
+        max_wait_secs: Some(30),
+        ..Default::default()
+    };
+    assert!(equal.validate().is_ok());
+}
+
+#[test]
+fn test_files_without_trigger_file_are_left_for_later() {
+    let dir = tempdir().unwrap();
+    for name in ["a.csv", "a.csv.ok", "b.csv"] {
+        fs::write(dir.path().join(name), "data").unwrap();
+    }
+    let profile = stable_profile(dir.path(), trigger_stability(None));
+
+    let mut tree = walk_local_dir(dir.path(), false).unwrap();
+    retain_ready_files(
+        &profile,
+        &TransferContext::default(),
+        dir.path(),
+        &mut tree,
+        local_file_state,
+    )
+    .unwrap();
+    assert_eq!(tree.files, [PathBuf::from("a.csv")]);
+}
+
+#[test]
+fn test_single_file_without_trigger_file_is_not_ready() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("report.csv");
+    fs::write(&path, "data").unwrap();
+    let profile = stable_profile(&path, trigger_stability(None));
+    let context = TransferContext::default();
+
+    let err = wait_until_ready(&profile, &context, &path, local_file_state).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AppError>(),
+        Some(AppError::SourceNotReady { reason, .. }) if reason.contains("report.csv.ok")
+    ));
+
+    fs::write(dir.path().join("report.csv.ok"), "").unwrap();
+    wait_until_ready(&profile, &context, &path, local_file_state).unwrap();
+}
+
+#[test]
+fn test_growing_file_is_skipped_until_stable() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("done.csv"), "complete").unwrap();
+    let growing = dir.path().join("growing.csv");
+    fs::write(&growing, "").unwrap();
+    let writer = std::thread::spawn(move || {
+        let mut file = OpenOptions::new().append(true).open(growing).unwrap();
+        for _ in 0..15 {
+            file.write_all(b"row\n").unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    });
+
+    let profile = stable_profile(
+        dir.path(),
+        Stability {
+            stable_secs: Some(1),
+            max_wait_secs: Some(2),
+            ..Default::default()
+        },
+    );
+    let mut tree = walk_local_dir(dir.path(), false).unwrap();
+    retain_ready_files(
+        &profile,
+        &TransferContext::default(),
+        dir.path(),
+        &mut tree,
+        local_file_state,
+    )
+    .unwrap();
+    writer.join().unwrap();
+    assert_eq!(tree.files, [PathBuf::from("done.csv")]);
+}
+
+#[test]
+fn test_stability_wait_stops_on_cancel() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("report.csv");
+    fs::write(&path, "data").unwrap();
+    let profile = stable_profile(
+        &path,
+        Stability {
+            stable_secs: Some(30),
+            ..Default::default()
+        },
+    );
+    let context = TransferContext::default();
+    context.cancel.cancel();
+
+    let err = wait_until_ready(&profile, &context, &path, local_file_state).unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::Cancelled)));
+}
+
+#[test]
+fn test_trigger_file_deleted_after_transfer() {
+    let dir = tempdir().unwrap();
+    let source = dir.path().join("report.csv");
+    let trigger = dir.path().join("report.csv.ok");
+    fs::write(&source, "data").unwrap();
+    fs::write(&trigger, "").unwrap();
+    let files = [TransferredFile::single(&source, Path::new("/remote/report.csv"))];
+
+    let keep = stable_profile(dir.path(), trigger_stability(None));
+    dispose_sources(&keep, &files, None).unwrap();
+    assert!(trigger.exists());
+
+    let delete = stable_profile(dir.path(), trigger_stability(Some(true)));
+    dispose_sources(&delete, &files, None).unwrap();
+    assert!(!trigger.exists());
+    assert!(source.exists());
+}
+
+#[test]
+fn test_watch_fires_on_trigger_file() {
+    let mut profile = stable_profile(Path::new("/data/out/*.csv"), trigger_stability(None));
+    profile.source.trigger.kind = TriggerType::Watch;
+    let watched = WatchedProfile::from_profile(&profile).unwrap().unwrap();
+
+    assert!(watched.matches(Path::new("/data/out/report.csv.ok")));
+    assert!(watched.matches(Path::new("/data/out/report.csv")));
+    assert!(!watched.matches(Path::new("/data/out/report.txt.ok")));
+}
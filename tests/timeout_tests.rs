@@ -0,0 +1,81 @@
+use std::io;
+use std::net::TcpListener;
+use std::time::Duration;
+
+use vento::*;
+
+#[test]
+fn test_timeouts_yaml_parsing() {
+    let config: AppConfig = serde_yaml::from_str(
+        r#"
+defaultProfileFile: /etc/vento/profiles.yaml
+timeouts:
+  connectSecs: 5
+  operationSecs: 0
+  keepaliveSecs: 15
+"#,
+    )
+    .unwrap();
+    let timeouts = config.timeouts.unwrap();
+    assert_eq!(timeouts.connect(), Some(Duration::from_secs(5)));
+    assert_eq!(timeouts.handshake_secs(), DEFAULT_HANDSHAKE_TIMEOUT_SECS);
+    // 0はタイムアウトなし
+    // 0 disables the timeout
+    assert_eq!(timeouts.operation_ms(), 0);
+    assert_eq!(timeouts.keepalive_secs(), 15);
+}
+
+#[test]
+fn test_profile_timeouts_override_app_config() {
+    let app = TimeoutSettings {
+        connect_secs: Some(10),
+        handshake_secs: Some(20),
+        operation_secs: Some(30),
+        keepalive_secs: Some(40),
+    };
+    let profile = TimeoutSettings {
+        connect_secs: Some(1),
+        ..Default::default()
+    };
+    let merged = profile.or(&app);
+    assert_eq!(merged.connect_secs, Some(1));
+    assert_eq!(merged.handshake_secs, Some(20));
+    assert_eq!(merged.operation_secs, Some(30));
+    assert_eq!(merged.keepalive_secs, Some(40));
+}
+
+#[test]
+fn test_connect_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let timeouts = TimeoutSettings {
+        connect_secs: Some(2),
+        ..Default::default()
+    };
+    assert!(connect_tcp("127.0.0.1", port, &timeouts).is_ok());
+
+    // 接続拒否はタイムアウトとして扱わない
+    // A refused connection is not reported as a timeout
+    drop(listener);
+    let err = connect_tcp("127.0.0.1", port, &timeouts).unwrap_err();
+    assert!(!is_timeout(&err));
+    assert_eq!(classify_error(&err), ErrorClass::Connect);
+}
+
+#[test]
+fn test_surface_timeout() {
+    let timed_out: anyhow::Error = io::Error::from(io::ErrorKind::TimedOut).into();
+    let err = surface_timeout(timed_out.context("Failed to copy data"), "transferring", 30);
+    match err.downcast_ref::<AppError>() {
+        Some(AppError::Timeout { operation, secs }) => {
+            assert_eq!(operation, "transferring");
+            assert_eq!(*secs, 30);
+        }
+        other => panic!("expected AppError::Timeout, got {:?}", other),
+    }
+    assert_eq!(classify_error(&err), ErrorClass::Timeout);
+
+    let other: anyhow::Error = io::Error::from(io::ErrorKind::PermissionDenied).into();
+    let err = surface_timeout(other, "transferring", 30);
+    assert!(err.downcast_ref::<AppError>().is_none());
+}
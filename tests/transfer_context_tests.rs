@@ -0,0 +1,84 @@
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::{Arc, Barrier};
+use std::time::Duration;
+
+use vento::*;
+
+fn file(name: &str, bytes: u64) -> TransferredFile {
+    TransferredFile {
+        source: PathBuf::from("/src").join(name),
+        destination: PathBuf::from("/dst").join(name),
+        relative: PathBuf::from(name),
+        size: bytes,
+        bytes,
+        checksum: None,
+    }
+}
+
+#[tokio::test]
+async fn test_run_blocking_collects_outcome() {
+    let outcome = run_blocking(TransferContext::default(), |_| {
+        Ok((vec![file("a.csv", 10), file("b.csv", 32)], None))
+    })
+    .await
+    .unwrap();
+    assert_eq!(outcome.files.len(), 2);
+    assert_eq!(outcome.bytes, 42);
+}
+
+#[tokio::test]
+async fn test_run_blocking_does_not_start_when_cancelled() {
+    let context = TransferContext::default();
+    context.cancel.cancel();
+    let started = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let flag = Arc::clone(&started);
+    let result = run_blocking(context, move |_| {
+        flag.store(true, std::sync::atomic::Ordering::SeqCst);
+        Ok((Vec::new(), None))
+    })
+    .await;
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<AppError>(),
+        Some(AppError::Cancelled)
+    ));
+    assert!(!started.load(std::sync::atomic::Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn test_run_blocking_reports_cancellation_during_transfer() {
+    let context = TransferContext::default();
+    let cancel = context.cancel.clone();
+    let barrier = Arc::new(Barrier::new(2));
+    let transfer_barrier = Arc::clone(&barrier);
+
+    let task = tokio::spawn(run_blocking(context, move |context| {
+        transfer_barrier.wait();
+        // キャンセルされるまで読み込み続ける
+        // Keep reading until cancelled
+        let mut reader = CancellableReader::new(io::repeat(0), context);
+        let mut buf = [0u8; 64];
+        loop {
+            reader.read_exact(&mut buf)?;
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }));
+
+    tokio::task::spawn_blocking(move || barrier.wait()).await.unwrap();
+    cancel.cancel();
+    let result = task.await.unwrap();
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<AppError>(),
+        Some(AppError::Cancelled)
+    ));
+}
+
+#[test]
+fn test_cancellable_reader_stops_reading() {
+    let context = TransferContext::default();
+    let mut reader = CancellableReader::new(&b"payload"[..], &context);
+    let mut buf = [0u8; 3];
+    assert_eq!(reader.read(&mut buf).unwrap(), 3);
+    context.cancel.cancel();
+    assert!(reader.read(&mut buf).is_err());
+}
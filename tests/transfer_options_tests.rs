@@ -0,0 +1,73 @@
+use vento::*;
+
+#[test]
+fn test_options_from_app_config() {
+    let config: AppConfig = serde_yaml::from_str(
+        r#"
+maxFileSizeMb: 0
+timeouts:
+  connectSecs: 5
+bandwidthLimit: 20MiB/s
+"#,
+    )
+    .unwrap();
+    let options = TransferOptions::from_app_config(&config);
+    assert_eq!(options.max_file_size_mb, Some(0));
+    assert_eq!(options.buffer_size, DEFAULT_BUFFER_SIZE);
+    assert_eq!(options.timeouts.connect_secs, Some(5));
+    assert_eq!(options.verify, None);
+    assert_eq!(
+        options.bandwidth_cap,
+        Some(BandwidthLimit::Fixed(Bandwidth::BytesPerSec(20 * 1024 * 1024)))
+    );
+
+    let defaults = TransferOptions::from_app_config(&serde_yaml::from_str("logLevel: info\n").unwrap());
+    assert_eq!(defaults.max_file_size_mb, Some(DEFAULT_MAX_FILE_SIZE_MB));
+}
+
+#[test]
+fn test_profile_settings_take_precedence_over_options() {
+    let context = TransferContext::default().with_options(TransferOptions {
+        timeouts: TimeoutSettings {
+            connect_secs: Some(10),
+            operation_secs: Some(60),
+            ..Default::default()
+        },
+        verify: Some(VerifyMethod::Size),
+        ..Default::default()
+    });
+
+    let profile = TransferProfile {
+        timeouts: Some(TimeoutSettings {
+            connect_secs: Some(3),
+            ..Default::default()
+        }),
+        verify: Some(VerifyMethod::Sha256),
+        ..Default::default()
+    };
+    let timeouts = context.timeouts(&profile);
+    assert_eq!(timeouts.connect_secs, Some(3));
+    assert_eq!(timeouts.operation_secs, Some(60));
+    assert_eq!(context.verify_method(&profile), Some(&VerifyMethod::Sha256));
+
+    let plain = TransferProfile::default();
+    assert_eq!(context.timeouts(&plain).connect_secs, Some(10));
+    assert_eq!(context.verify_method(&plain), Some(&VerifyMethod::Size));
+}
+
+#[test]
+fn test_contexts_carry_independent_options() {
+    let first = TransferContext::default().with_options(TransferOptions {
+        buffer_size: 64 * 1024,
+        ..Default::default()
+    });
+    let second = TransferContext::default();
+    assert_eq!(first.options.buffer_size, 64 * 1024);
+    assert_eq!(second.options.buffer_size, DEFAULT_BUFFER_SIZE);
+    // 複製したコンテキストはキャンセル用トークンを共有する
+    // Cloned contexts share the cancellation token
+    let clone = first.clone();
+    first.cancel.cancel();
+    assert!(clone.is_cancelled());
+    assert!(!second.is_cancelled());
+}
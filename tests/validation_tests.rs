@@ -7,6 +7,11 @@ fn valid_auth() -> Authentication {
         password_ref: Some("secret".to_string()),
         private_key_ref: None,
         ssh_config_alias: None,
+        ssh_config_file: None,
+        passphrase_ref: None,
+        prompts: None,
+        host_key_fingerprint: None,
+        jump_hosts: None,
     }
 }
 
@@ -21,7 +26,13 @@ fn test_source_validate_local_success() {
         trigger: Trigger {
             kind: TriggerType::Manual,
             schedule: None,
+            missed_runs: None,
+            overlap: None,
+            debounce_ms: None,
         },
+        filter: None,
+        on_success: None,
+        stability: None,
     };
     assert!(source.validate().is_ok());
 }
@@ -37,7 +48,13 @@ fn test_source_validate_sftp_missing_authentication() {
         trigger: Trigger {
             kind: TriggerType::Manual,
             schedule: None,
+            missed_runs: None,
+            overlap: None,
+            debounce_ms: None,
         },
+        filter: None,
+        on_success: None,
+        stability: None,
     };
     let result = source.validate();
     assert!(matches!(result, Err(AppError::Validation(msg)) if msg.contains("authentication")));
@@ -76,6 +93,11 @@ fn test_authentication_password_missing_password_ref() {
         password_ref: None,
         private_key_ref: None,
         ssh_config_alias: None,
+        ssh_config_file: None,
+        passphrase_ref: None,
+        prompts: None,
+        host_key_fingerprint: None,
+        jump_hosts: None,
     };
     let result = auth.validate();
     assert!(
@@ -91,16 +113,39 @@ fn test_authentication_ssh_config_missing_alias() {
         password_ref: None,
         private_key_ref: None,
         ssh_config_alias: None,
+        ssh_config_file: None,
+        passphrase_ref: None,
+        prompts: None,
+        host_key_fingerprint: None,
+        jump_hosts: None,
     };
     let result = auth.validate();
     assert!(matches!(result, Err(AppError::Validation(msg)) if msg.contains("sshConfigAlias")));
 }
 
+#[test]
+fn test_authentication_agent_needs_no_key_reference() {
+    let auth: Authentication = serde_yaml::from_str(
+        r#"
+method: agent
+username: deploy
+"#,
+    )
+    .unwrap();
+    assert_eq!(auth.method, AuthenticationMethod::Agent);
+    assert!(auth.validate().is_ok());
+    assert_eq!("agent".parse(), Ok(AuthenticationMethod::Agent));
+    assert_eq!(AuthenticationMethod::Agent.to_string(), "agent");
+}
+
 #[test]
 fn test_trigger_manual_ok() {
     let trigger = Trigger {
         kind: TriggerType::Manual,
         schedule: None,
+        missed_runs: None,
+        overlap: None,
+        debounce_ms: None,
     };
     assert!(trigger.validate().is_ok());
 }
@@ -110,6 +155,9 @@ fn test_trigger_schedule_valid_cron() {
     let trigger = Trigger {
         kind: TriggerType::Schedule,
         schedule: Some("0 0 * * * *".into()), // 毎時0分
+        missed_runs: None,
+        overlap: None,
+        debounce_ms: None,
     };
     assert!(trigger.validate().is_ok());
 }
@@ -119,6 +167,9 @@ fn test_trigger_schedule_missing_schedule() {
     let trigger = Trigger {
         kind: TriggerType::Schedule,
         schedule: None,
+        missed_runs: None,
+        overlap: None,
+        debounce_ms: None,
     };
     let result = trigger.validate();
     assert!(matches!(result, Err(AppError::MissingSchedule)));
@@ -129,7 +180,28 @@ fn test_trigger_schedule_invalid_cron() {
     let trigger = Trigger {
         kind: TriggerType::Schedule,
         schedule: Some("invalid_cron".into()),
+        missed_runs: None,
+        overlap: None,
+        debounce_ms: None,
     };
     let result = trigger.validate();
     assert!(matches!(result, Err(AppError::InvalidCronSchedule { .. })));
 }
+
+#[test]
+fn test_ssh_config_endpoints_may_omit_port() {
+    let destination: Destination = serde_yaml::from_str(
+        r#"
+type: sftp
+path: /tmp/remote
+host: prod-sftp
+authentication:
+  method: ssh_config
+  username: deploy
+  sshConfigAlias: prod-sftp
+"#,
+    )
+    .unwrap();
+    assert!(destination.port.is_none());
+    assert!(destination.validate().is_ok());
+}
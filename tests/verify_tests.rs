@@ -0,0 +1,39 @@
+use std::io::Read;
+use std::path::Path;
+
+use vento::*;
+
+#[test]
+fn test_checksum_sha256() {
+    let mut checksum = Checksum::new(&VerifyMethod::Sha256);
+    checksum.update(b"abc");
+    assert_eq!(
+        checksum.finalize(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn test_checksum_md5_via_hashing_reader() {
+    let mut checksum = Checksum::new(&VerifyMethod::Md5);
+    let mut out = Vec::new();
+    HashingReader::new(&b"abc"[..], Some(&mut checksum))
+        .read_to_end(&mut out)
+        .unwrap();
+
+    assert_eq!(out, b"abc");
+    assert_eq!(checksum.finalize(), "900150983cd24fb0d6963f7d28e17f72");
+}
+
+#[test]
+fn test_checksum_size() {
+    let mut checksum = Checksum::new(&VerifyMethod::Size);
+    checksum.update_from_reader(&[0u8; 1234][..]).unwrap();
+    assert_eq!(checksum.finalize(), "1234");
+}
+
+#[test]
+fn test_checksum_mismatch_error() {
+    let result = ensure_checksums_match(Path::new("/upload/a.csv"), &VerifyMethod::Size, "10", "8");
+    assert!(matches!(result, Err(AppError::ChecksumMismatch { local, remote, .. }) if local == "10" && remote == "8"));
+}
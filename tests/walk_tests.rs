@@ -0,0 +1,42 @@
+use std::fs;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+use vento::walk_local_dir;
+
+#[test]
+fn test_walk_local_dir_collects_nested_files_and_dirs() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("daily/2025")).unwrap();
+    fs::create_dir_all(root.join("empty")).unwrap();
+    fs::write(root.join("summary.csv"), "a").unwrap();
+    fs::write(root.join("daily/2025/report.csv"), "b").unwrap();
+
+    let mut tree = walk_local_dir(root, true).unwrap();
+    tree.dirs.sort();
+    tree.files.sort();
+
+    assert_eq!(
+        tree.dirs,
+        vec![
+            PathBuf::from("daily"),
+            PathBuf::from("daily/2025"),
+            PathBuf::from("empty"),
+        ]
+    );
+    assert_eq!(
+        tree.files,
+        vec![
+            PathBuf::from("daily/2025/report.csv"),
+            PathBuf::from("summary.csv"),
+        ]
+    );
+}
+
+#[test]
+fn test_walk_local_dir_missing_root_fails() {
+    let dir = tempdir().unwrap();
+    let result = walk_local_dir(&dir.path().join("missing"), true);
+    assert!(result.is_err());
+}
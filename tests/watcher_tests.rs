@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use notify::event::{AccessKind, AccessMode, CreateKind, DataChange, Flag, ModifyKind, RenameMode};
+use notify::{Event, EventKind};
+use vento::*;
+
+fn watch_profile(profile_id: &str, path: &str, debounce_ms: Option<u64>) -> TransferProfile {
+    let mut profile = TransferProfile {
+        profile_id: profile_id.to_string(),
+        ..Default::default()
+    };
+    profile.source.path = path.to_string();
+    profile.source.trigger = Trigger {
+        kind: TriggerType::Watch,
+        schedule: None,
+        missed_runs: None,
+        overlap: None,
+        debounce_ms,
+    };
+    profile
+}
+
+fn event(kind: EventKind, paths: &[&str]) -> Event {
+    paths
+        .iter()
+        .fold(Event::new(kind), |event, path| event.add_path(path.into()))
+}
+
+#[test]
+fn test_watched_profile_matches_source_glob() {
+    let watched = WatchedProfile::from_profile(&watch_profile("csv", "/data/out/*.csv", None))
+        .unwrap()
+        .unwrap();
+    assert_eq!(watched.root, Path::new("/data/out"));
+    assert_eq!(watched.overlap, OverlapPolicy::Queue);
+    assert_eq!(
+        watched.debounce,
+        Duration::from_millis(DEFAULT_WATCH_DEBOUNCE_MS)
+    );
+
+    assert!(watched.matches(Path::new("/data/out/report.csv")));
+    assert!(!watched.matches(Path::new("/data/out/report.csv.part")));
+    assert!(!watched.matches(Path::new("/data/other/report.csv")));
+    // recursive でなければサブディレクトリは対象外
+    // Subdirectories are ignored unless recursive
+    assert!(!watched.matches(Path::new("/data/out/nested/report.csv")));
+}
+
+#[test]
+fn test_watched_profile_for_single_file() {
+    let watched = WatchedProfile::from_profile(&watch_profile("one", "/data/out/daily.csv", None))
+        .unwrap()
+        .unwrap();
+    assert_eq!(watched.root, Path::new("/data/out"));
+    assert!(watched.matches(Path::new("/data/out/daily.csv")));
+    assert!(!watched.matches(Path::new("/data/out/weekly.csv")));
+}
+
+#[test]
+fn test_non_watch_triggers_are_ignored() {
+    let mut profile = watch_profile("manual", "/data/out/*.csv", None);
+    profile.source.trigger.kind = TriggerType::Manual;
+    assert!(WatchedProfile::from_profile(&profile).unwrap().is_none());
+}
+
+#[test]
+fn test_file_drop_events() {
+    assert!(is_file_drop(&EventKind::Create(CreateKind::File)));
+    assert!(is_file_drop(&EventKind::Access(AccessKind::Close(AccessMode::Write))));
+    assert!(is_file_drop(&EventKind::Modify(ModifyKind::Name(RenameMode::To))));
+    assert!(!is_file_drop(&EventKind::Create(CreateKind::Folder)));
+    assert!(!is_file_drop(&EventKind::Modify(ModifyKind::Data(DataChange::Content))));
+    assert!(!is_file_drop(&EventKind::Modify(ModifyKind::Name(RenameMode::From))));
+}
+
+#[test]
+fn test_debouncer_waits_for_quiet_period() {
+    let start = Instant::now();
+    let debounce = Duration::from_millis(500);
+    let mut debouncer = Debouncer::default();
+
+    debouncer.touch("csv", start, debounce);
+    debouncer.touch("csv", start + Duration::from_millis(300), debounce);
+    assert_eq!(
+        debouncer.next_deadline(),
+        Some(start + Duration::from_millis(800))
+    );
+    assert!(debouncer.take_ready(start + Duration::from_millis(600)).is_empty());
+    assert_eq!(
+        debouncer.take_ready(start + Duration::from_millis(800)),
+        ["csv"]
+    );
+    assert!(debouncer.is_empty());
+}
+
+#[test]
+fn test_handle_event_routes_to_matching_profiles() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_string_lossy().to_string();
+    let mut watcher = FileWatcher::new(&[
+        watch_profile("csv", &format!("{}/*.csv", root), Some(100)),
+        watch_profile("txt", &format!("{}/*.txt", root), Some(100)),
+    ]);
+    assert_eq!(watcher.profiles().len(), 2);
+
+    let now = Instant::now();
+    let later = now + Duration::from_millis(100);
+    let csv = format!("{}/a.csv", root);
+    let tmp = format!("{}/a.tmp", root);
+    watcher.handle_event(
+        &event(EventKind::Modify(ModifyKind::Data(DataChange::Content)), &[&csv]),
+        now,
+    );
+    // リネームは移動先のパスで判定する
+    // Renames are judged by their destination path
+    watcher.handle_event(
+        &event(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), &[&csv, &tmp]),
+        now,
+    );
+    assert!(watcher.take_ready(later).is_empty());
+
+    watcher.handle_event(
+        &event(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), &[&tmp, &csv]),
+        now,
+    );
+    watcher.handle_event(
+        &event(EventKind::Access(AccessKind::Close(AccessMode::Write)), &[&csv]),
+        now,
+    );
+    assert_eq!(watcher.take_ready(later), ["csv"]);
+
+    // 取りこぼしが起きた場合はすべてのプロファイルを実行する
+    // Dropped events run every profile
+    watcher.handle_event(&Event::new(EventKind::Other).set_flag(Flag::Rescan), now);
+    assert_eq!(watcher.take_ready(later), ["csv", "txt"]);
+}
+
+#[tokio::test]
+async fn test_watcher_fires_after_file_is_written() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_string_lossy().to_string();
+    let mut watcher = FileWatcher::new(&[
+        watch_profile("csv", &format!("{}/*.csv", root), Some(200)),
+        watch_profile("txt", &format!("{}/*.txt", root), Some(200)),
+    ]);
+
+    let path = dir.path().join("report.csv");
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        fs::write(path, "id,value\n1,2\n").unwrap();
+    });
+
+    let ready = tokio::time::timeout(Duration::from_secs(5), watcher.next_ready())
+        .await
+        .expect("the watch trigger did not fire");
+    assert_eq!(ready, ["csv"]);
+}
+
+#[test]
+fn test_watch_trigger_requires_local_source() {
+    let profile: TransferProfile = serde_yaml::from_str(
+        r#"
+profileId: remote-watch
+source:
+  type: sftp
+  path: /outbox/*.csv
+  host: example.com
+  port: 22
+  authentication:
+    method: password
+    username: user
+    passwordRef: VENTO_WATCH_TEST_PASSWORD
+  trigger:
+    type: watch
+    debounceMs: 500
+destination:
+  type: local
+  path: /tmp/inbox
+transferProtocol:
+  protocol: SFTP
+"#,
+    )
+    .unwrap();
+    assert_eq!(profile.source.trigger.debounce(), Duration::from_millis(500));
+    assert!(matches!(
+        profile.source.validate(),
+        Err(AppError::Validation(message)) if message.contains("watch")
+    ));
+}